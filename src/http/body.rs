@@ -0,0 +1,182 @@
+use std::pin::Pin;
+
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// How much a reader-backed body pulls per read.
+const READ_BUF_SIZE: usize = 8 * 1024;
+
+type BoxStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+enum Kind {
+    Full(Option<Bytes>),
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+    Stream(BoxStream),
+}
+
+/// Response content: a buffer in memory, or something read incrementally.
+///
+/// `Writer::send` drives the body, sending `Content-Length` when the size is
+/// known up front (buffers, files, sized readers) and chunked encoding when it
+/// isn't (plain readers and streams).
+pub struct Body {
+    kind: Kind,
+    len: Option<usize>,
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Body::from(Bytes::new())
+    }
+
+    /// A body read from `reader` until EOF; its length is not known.
+    pub fn from_reader<R: AsyncRead + Send + 'static>(reader: R) -> Self {
+        Body { kind: Kind::Reader(Box::pin(reader)), len: None }
+    }
+
+    /// A body of exactly `len` bytes read from `reader`. The `Writer` fails
+    /// the response if the reader ends early.
+    pub fn from_sized_reader<R: AsyncRead + Send + 'static>(reader: R, len: usize) -> Self {
+        Body { kind: Kind::Reader(Box::pin(reader.take(len as u64))), len: Some(len) }
+    }
+
+    /// A body made of the stream's items, e.g. a proxied `reqwest` response.
+    pub fn from_stream<S, E>(stream: S) -> Self
+        where S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+              E: Into<Box<dyn std::error::Error + Send + Sync>>
+    {
+        let stream = stream.map(|item| item.map_err(std::io::Error::other));
+        Body { kind: Kind::Stream(Box::pin(stream)), len: None }
+    }
+
+    /// A body with the file's contents, sized from its metadata. The file
+    /// should be positioned at its start.
+    pub async fn from_file(file: File) -> Result<Self, std::io::Error> {
+        let len = usize::try_from(file.metadata().await?.len())
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "file too large"))?;
+        Ok(Self::from_sized_reader(file, len))
+    }
+
+    /// The size in bytes, if known without reading the body.
+    pub fn len(&self) -> Option<usize> {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == Some(0)
+    }
+
+    /// The contents, if the body is held in memory.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.kind {
+            Kind::Full(bytes) => Some(bytes.as_deref().unwrap_or_default()),
+            _ => None,
+        }
+    }
+
+    /// The next piece of the body, or `None` once it is exhausted.
+    pub async fn next_chunk(&mut self) -> Result<Option<Bytes>, std::io::Error> {
+        match &mut self.kind {
+            Kind::Full(bytes) => Ok(bytes.take().filter(|b| !b.is_empty())),
+            Kind::Reader(reader) => {
+                let mut buf = BytesMut::with_capacity(READ_BUF_SIZE);
+                let n = reader.read_buf(&mut buf).await?;
+                Ok((n > 0).then(|| buf.freeze()))
+            },
+            Kind::Stream(stream) => {
+                // skip empty items so they can't be mistaken for the last chunk
+                while let Some(item) = stream.next().await {
+                    let bytes = item?;
+                    if !bytes.is_empty() {
+                        return Ok(Some(bytes));
+                    }
+                }
+                Ok(None)
+            },
+        }
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(bytes: Bytes) -> Self {
+        let len = bytes.len();
+        Body { kind: Kind::Full(Some(bytes)), len: Some(len) }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Body::from(Bytes::from(bytes))
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        Body::from(Bytes::copy_from_slice(bytes))
+    }
+}
+
+impl From<String> for Body {
+    fn from(s: String) -> Self {
+        Body::from(Bytes::from(s))
+    }
+}
+
+impl From<&str> for Body {
+    fn from(s: &str) -> Self {
+        Body::from(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn collect(mut body: Body) -> Vec<u8> {
+        let mut out = Vec::new();
+        while let Some(chunk) = body.next_chunk().await.unwrap() {
+            out.extend_from_slice(&chunk);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn test_buffered_body() {
+        let body = Body::from("hello");
+        assert_eq!(Some(5), body.len());
+        assert_eq!(Some(&b"hello"[..]), body.as_bytes());
+        assert_eq!(b"hello".to_vec(), collect(body).await);
+
+        let mut empty = Body::empty();
+        assert!(empty.is_empty());
+        assert_eq!(None, empty.next_chunk().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reader_bodies() {
+        let body = Body::from_reader(&b"streamed"[..]);
+        assert_eq!(None, body.len());
+        assert_eq!(b"streamed".to_vec(), collect(body).await);
+
+        // a sized reader never yields more than it promised
+        let body = Body::from_sized_reader(&b"streamed"[..], 6);
+        assert_eq!(Some(6), body.len());
+        assert_eq!(b"stream".to_vec(), collect(body).await);
+    }
+
+    #[tokio::test]
+    async fn test_stream_body_skips_empty_items() {
+        let items: Vec<Result<Bytes, std::io::Error>> = vec![Ok(Bytes::from("a")), Ok(Bytes::new()), Ok(Bytes::from("b"))];
+        let body = Body::from_stream(futures_util::stream::iter(items));
+        assert_eq!(None, body.len());
+        assert_eq!(b"ab".to_vec(), collect(body).await);
+    }
+}
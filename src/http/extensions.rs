@@ -0,0 +1,86 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A map holding at most one value of each type, for data that middleware
+/// attaches to a `Request` (an authenticated user, a request id, timings)
+/// and later handlers read back by type.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Extensions { map: HashMap::new() }
+    }
+
+    /// Stores `value`, returning the previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map.insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>()).and_then(|value| value.downcast_ref())
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>()).and_then(|value| value.downcast_mut())
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map.remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.map.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct UserId(u32);
+
+    #[test]
+    fn test_one_value_per_type() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.is_empty());
+
+        assert_eq!(None, extensions.insert(UserId(1)));
+        assert_eq!(None, extensions.insert("request-42"));
+        assert_eq!(Some(UserId(1)), extensions.insert(UserId(2)));
+        assert_eq!(2, extensions.len());
+
+        assert_eq!(Some(&UserId(2)), extensions.get::<UserId>());
+        assert_eq!(Some(&"request-42"), extensions.get::<&str>());
+        assert_eq!(None, extensions.get::<String>());
+
+        extensions.get_mut::<UserId>().unwrap().0 += 1;
+        assert_eq!(Some(UserId(3)), extensions.remove::<UserId>());
+        assert!(!extensions.contains::<UserId>());
+    }
+}
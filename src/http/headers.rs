@@ -1,26 +1,306 @@
-use std::collections::HashMap;
-use crate::http::request::ParseError;
+use std::borrow::Cow;
+use crate::http::request::{ParseError, ParseErrorKind};
+
+/// A header field name.
+///
+/// Keeps the spelling it was created with, which is what goes on the wire, but
+/// compares and hashes case-insensitively, so `Content-Length` and
+/// `content-length` are the same field everywhere in `Headers`. Names built
+/// with `From` are not checked until the `Writer` sends them.
+#[derive(Debug, Clone)]
+pub struct HeaderName(Cow<'static, str>);
+
+impl HeaderName {
+    pub const ACCEPT: HeaderName = HeaderName::from_static("Accept");
+    pub const ALLOW: HeaderName = HeaderName::from_static("Allow");
+    pub const CACHE_CONTROL: HeaderName = HeaderName::from_static("Cache-Control");
+    pub const CONNECTION: HeaderName = HeaderName::from_static("Connection");
+    pub const CONTENT_LENGTH: HeaderName = HeaderName::from_static("Content-Length");
+    pub const CONTENT_TYPE: HeaderName = HeaderName::from_static("Content-Type");
+    pub const DATE: HeaderName = HeaderName::from_static("Date");
+    pub const ETAG: HeaderName = HeaderName::from_static("ETag");
+    pub const HOST: HeaderName = HeaderName::from_static("Host");
+    pub const RANGE: HeaderName = HeaderName::from_static("Range");
+    pub const TRAILER: HeaderName = HeaderName::from_static("Trailer");
+    pub const TRANSFER_ENCODING: HeaderName = HeaderName::from_static("Transfer-Encoding");
+
+    pub const fn from_static(name: &'static str) -> Self {
+        HeaderName(Cow::Borrowed(name))
+    }
+
+    /// The name as it was spelled when created.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for HeaderName {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for HeaderName {}
+
+impl PartialEq<str> for HeaderName {
+    fn eq(&self, other: &str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl PartialEq<&str> for HeaderName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl std::hash::Hash for HeaderName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for b in self.0.bytes() {
+            state.write_u8(b.to_ascii_lowercase());
+        }
+    }
+}
+
+impl AsRef<str> for HeaderName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&'static str> for HeaderName {
+    fn from(name: &'static str) -> Self {
+        HeaderName::from_static(name)
+    }
+}
+
+impl From<String> for HeaderName {
+    fn from(name: String) -> Self {
+        HeaderName(Cow::Owned(name))
+    }
+}
+
+impl From<&HeaderName> for HeaderName {
+    fn from(name: &HeaderName) -> Self {
+        name.clone()
+    }
+}
+
+/// Parses a name received off the wire, rejecting anything that isn't a token.
+impl std::str::FromStr for HeaderName {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !Headers::is_valid_field_name(s) {
+            return Err(ParseErrorKind::InvalidHeaderName.into());
+        }
+        Ok(HeaderName(Cow::Owned(s.to_string())))
+    }
+}
+
+impl std::fmt::Display for HeaderName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A header field value, kept byte-for-byte as received.
+///
+/// Values may carry obs-text (bytes 0x80-0xFF), which RFC 9110 lets through
+/// but gives no charset, so they are never decoded lossily; `to_str` only
+/// succeeds for visible ASCII. Values built with `From` are not checked until
+/// the `Writer` sends them.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct HeaderValue(Vec<u8>);
+
+impl HeaderValue {
+    /// Checks `bytes` against the field-value grammar, rejecting NUL, bare CR
+    /// or LF and every other control character apart from HTAB. The error's
+    /// offset is the position of the first invalid byte.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if let Some(pos) = Self::first_invalid(bytes) {
+            return Err(ParseError::new(ParseErrorKind::InvalidHeaderValue).at(pos));
+        }
+        Ok(HeaderValue(bytes.to_vec()))
+    }
 
-pub struct Headers(HashMap<String, String>);
+    // field-vchar = VCHAR / obs-text, plus SP and HTAB between them
+    fn first_invalid(bytes: &[u8]) -> Option<usize> {
+        bytes.iter().position(|&b| !(b == b'\t' || b == b' ' || (b > 0x20 && b != 0x7f)))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The value as a string, if it is entirely visible ASCII, SP or HTAB.
+    pub fn to_str(&self) -> Result<&str, ParseError> {
+        if !self.0.iter().all(|&b| b == b'\t' || (b' '..=b'~').contains(&b)) {
+            return Err(ParseErrorKind::InvalidHeaderValue.into());
+        }
+        // all ASCII, so always valid UTF-8
+        std::str::from_utf8(&self.0).map_err(|_| ParseErrorKind::InvalidHeaderValue.into())
+    }
+
+    pub(crate) fn check(&self) -> Result<(), std::io::Error> {
+        if Self::first_invalid(&self.0).is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "header value contains control characters"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for HeaderValue {
+    fn from(value: String) -> Self {
+        HeaderValue(value.into_bytes())
+    }
+}
+
+impl From<&str> for HeaderValue {
+    fn from(value: &str) -> Self {
+        HeaderValue(value.as_bytes().to_vec())
+    }
+}
+
+impl PartialEq<str> for HeaderValue {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for HeaderValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<HeaderValue> for str {
+    fn eq(&self, other: &HeaderValue) -> bool {
+        self.as_bytes() == other.0
+    }
+}
+
+impl std::fmt::Debug for HeaderValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\"", self.0.escape_ascii())
+    }
+}
+
+/// Writes the value, escaping any obs-text bytes.
+impl std::fmt::Display for HeaderValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.escape_ascii())
+    }
+}
+
+/// Header fields in the order they were received or added.
+///
+/// Names keep the case they were given in, for serialisation, but every lookup
+/// is case-insensitive. Repeated fields stay separate entries rather than being
+/// folded into one comma-joined value, which would corrupt `Set-Cookie`.
+#[derive(Debug)]
+pub struct Headers(Vec<(HeaderName, HeaderValue)>);
+
+impl Default for Headers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Headers {
 
     pub fn new() -> Self {
-        Headers(HashMap::new())
+        Headers(Vec::new())
+    }
+
+    /// Replaces every existing value of `key` with `value`; same as `set`.
+    pub fn insert(&mut self, key: impl Into<HeaderName>, value: impl Into<HeaderValue>) {
+        self.set(key, value);
+    }
+
+    /// Replaces every existing value of `key` with `value`. The new field takes
+    /// the position of the first one it replaces.
+    pub fn set(&mut self, key: impl Into<HeaderName>, value: impl Into<HeaderValue>) {
+        let key = key.into();
+        let value = value.into();
+        match self.0.iter().position(|(k, _)| *k == key) {
+            Some(pos) => {
+                let mut i = 0;
+                self.0.retain(|(k, _)| {
+                    i += 1;
+                    i - 1 <= pos || *k != key
+                });
+                self.0[pos] = (key, value);
+            },
+            None => self.0.push((key, value)),
+        }
+    }
+
+    /// Adds a value without touching existing values of the same name.
+    pub fn append(&mut self, key: impl Into<HeaderName>, value: impl Into<HeaderValue>) {
+        self.0.push((key.into(), value.into()));
+    }
+
+    pub fn remove_entry(&mut self, key: impl AsRef<str>) {
+        self.0.retain(|(k, _)| *k != *key.as_ref());
+    }
+
+    /// The first value of `key`. Use `get_all` for fields that may repeat.
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&HeaderValue> {
+        self.get_all(key).next()
+    }
+
+    /// The first value of `key` as a string; `None` if it is missing or not
+    /// visible ASCII.
+    pub fn get_str(&self, key: impl AsRef<str>) -> Option<&str> {
+        self.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn get_all(&self, key: impl AsRef<str>) -> impl Iterator<Item = &HeaderValue> {
+        self.0.iter()
+            .filter(move |(k, _)| *k == *key.as_ref())
+            .map(|(_, v)| v)
     }
 
-    pub fn insert(&mut self, key: String, value: String) {
-        self.0.insert(key, value);
+    pub fn contains_key(&self, key: impl AsRef<str>) -> bool {
+        self.get(key).is_some()
     }
 
-    pub fn remove_entry(&mut self, key: &str) {
-        self.0.remove_entry(key);
+    /// Whether any value of a comma-separated list field contains `token`,
+    /// e.g. `close` in `Connection`.
+    pub fn contains_token(&self, key: impl AsRef<str>, token: &str) -> bool {
+        self.get_all(key).any(|v| v.to_str().is_ok_and(|v| has_token(v, token)))
     }
 
-    pub fn get(&self, key: &str) -> Option<&String> {
-        self.0.get(&key.to_lowercase())
+    pub fn iter(&self) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+        self.0.iter().map(|(k, v)| (k, v))
     }
 
+    /// The fields as they go on the wire, each line ending in CRLF. Fails if a
+    /// name isn't a token or a value holds a control character that would
+    /// break the framing.
+    pub(crate) fn to_wire(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut data = Vec::new();
+        for (name, value) in &self.0 {
+            if !Self::is_valid_field_name(name.as_str()) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "header name is not a valid token"
+                ));
+            }
+            value.check()?;
+            data.extend_from_slice(name.as_str().as_bytes());
+            data.extend_from_slice(b": ");
+            data.extend_from_slice(value.as_bytes());
+            data.extend_from_slice(b"\r\n");
+        }
+        Ok(data)
+    }
+
+    /// Number of field lines, counting repeated names separately.
     pub fn len(&self) -> usize {
         self.0.len()
     }
@@ -29,14 +309,12 @@ impl Headers {
         self.0.is_empty()
     }
 
-    fn is_valid_tchar(c: char) -> bool {
-        match c {
-            'A'..='Z' | 'a'..='z' => true,
-            '0'..='9' => true,
+    pub(crate) fn is_valid_tchar(c: char) -> bool {
+        matches!(c,
+            'A'..='Z' | 'a'..='z' |
+            '0'..='9' |
             '!' | '#' | '$' | '%' | '&' | '\'' | '*' | '+' | '-' | '.' |
-            '^' | '_' | '`' | '|' | '~' => true,
-            _ => false,
-        }
+            '^' | '_' | '`' | '|' | '~')
     }
 
     fn is_valid_field_name(name: &str) -> bool {
@@ -51,32 +329,33 @@ impl Headers {
                     return Ok((2, true));
                 }
 
-                let line = String::from_utf8_lossy(&data[..line_end]);
+                let line = &data[..line_end];
 
-                if let Some(colon_pos) = line.find(':') {
+                if let Some(colon_pos) = line.iter().position(|&b| b == b':') {
                     let field_name = &line[..colon_pos];
                     let field_value = &line[colon_pos+1..];
 
-                    if field_name.ends_with(' ') {
-                        return Err(ParseError::InvalidFormat("found space between field_name and colon".to_string()));
+                    // whitespace before the colon is a smuggling vector and leading
+                    // whitespace is obs-fold; RFC 9112 section 5.1 says reject both
+                    if let Some(space) = field_name.iter().position(|b| b.is_ascii_whitespace()) {
+                        return Err(ParseError::new(ParseErrorKind::InvalidHeaderLine).at(space));
                     }
 
-                    let key_trimmed = field_name.trim();
+                    let key: HeaderName = std::str::from_utf8(field_name)
+                        .map_err(|_| ParseError::from(ParseErrorKind::InvalidHeaderName))
+                        .and_then(|name| name.parse())
+                        .map_err(|e| e.at(0))?;
+                    // OWS around the value is only ever SP or HTAB
+                    let start = field_value.iter().position(|&b| b != b' ' && b != b'\t').unwrap_or(field_value.len());
+                    let end = field_value.iter().rposition(|&b| b != b' ' && b != b'\t').map_or(start, |i| i + 1);
+                    let value = HeaderValue::from_bytes(&field_value[start..end])
+                        .map_err(|e| e.shifted(colon_pos + 1 + start))?;
 
-                    if !Self::is_valid_field_name(&key_trimmed) {
-                        return Err(ParseError::InvalidFormat("found invalid chars within field_name".to_string()));
-                    }
-
-                    let key = field_name.trim().to_lowercase();
-                    let value = field_value.trim().to_string();
-
-                    self.0.entry(key).and_modify(|existing| {
-                        *existing = format!("{}, {}", existing, value);
-                    }).or_insert(value);
+                    self.append(key, value);
 
                     Ok((line_end+2, false))
                 } else {
-                    Err(ParseError::InvalidFormat("no colon found".to_string()))
+                    Err(ParseErrorKind::InvalidHeaderLine.into())
                 }
             } else {
                 Ok((0, false))
@@ -86,6 +365,11 @@ impl Headers {
 
 }
 
+/// Checks a comma-separated header value (e.g. `Connection`) for a token.
+pub(crate) fn has_token(value: &str, token: &str) -> bool {
+    value.split(',').any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
 impl std::fmt::Display for Headers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}",
@@ -118,23 +402,29 @@ mod test {
 
         let result = headers.parse(data);
 
-        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert_eq!(error, ParseErrorKind::InvalidHeaderLine);
+        // the first whitespace in the name
+        assert_eq!(Some(0), error.offset());
+    }
 
-        if let Err(ParseError::InvalidFormat(msg)) = result {
-            assert!(msg.contains("found space"));
-        } else {
-            panic!("Expected compilation error");
+    #[test]
+    fn test_whitespace_around_name_rejected() {
+        for (data, offset) in [(&b"Content-Length\t: 5\r\n"[..], 14), (b"Content-Length : 5\r\n", 14), (b" Host: localhost\r\n", 0), (b"\tHost: localhost\r\n", 0)] {
+            let error = Headers::new().parse(data).unwrap_err();
+            assert_eq!(error, ParseErrorKind::InvalidHeaderLine, "{:?}", data);
+            assert_eq!(Some(offset), error.offset(), "{:?}", data);
         }
     }
 
     #[test]
     fn test_valid_single_header_with_extra_whitespace() {
         let mut headers = Headers::new();
-        let data = b"          Host: localhost:42069    \r\n\r\n";
+        let data = b"Host: localhost:42069    \r\n\r\n";
 
         let (n, _done) = headers.parse(data).unwrap();
         assert_eq!("localhost:42069", headers.get("host").unwrap());
-        assert_eq!(37, n);
+        assert_eq!(27, n);
 
     }
 
@@ -166,13 +456,7 @@ mod test {
         let data = b"H@st: localhost:42069\r\n";
         
         let result = headers.parse(data);
-        assert!(result.is_err());
-        
-        if let Err(ParseError::InvalidFormat(msg)) = result {
-            assert!(msg.contains("invalid chars"));
-        } else {
-            panic!("Expected InvalidFormat error");
-    }
+        assert_eq!(result.unwrap_err(), ParseErrorKind::InvalidHeaderName);
     }
 
     #[test]
@@ -194,8 +478,8 @@ mod test {
         let data = b"Set-Person: prime-loves-zig\r\n";
         let (_n, done) = headers.parse(data).unwrap();
         
-        // Should combine with comma separator
-        assert_eq!("lane-loves-go, prime-loves-zig", headers.get("set-person").unwrap());
+        // Should keep both values, in order
+        assert_eq!(vec!["lane-loves-go", "prime-loves-zig"], headers.get_all("set-person").collect::<Vec<_>>());
         assert!(!done);
     }
 
@@ -212,14 +496,116 @@ mod test {
         // Parse second Set-Person header  
         let data2 = b"Set-Person: prime-loves-zig\r\n";
         let (_n2, done2) = headers.parse(data2).unwrap();
-        assert_eq!("lane-loves-go, prime-loves-zig", headers.get("set-person").unwrap());
+        assert_eq!(vec!["lane-loves-go", "prime-loves-zig"], headers.get_all("set-person").collect::<Vec<_>>());
         assert!(!done2);
         
         // Parse third Set-Person header
         let data3 = b"Set-Person: tj-loves-ocaml\r\n";
         let (_n3, done3) = headers.parse(data3).unwrap();
-        assert_eq!("lane-loves-go, prime-loves-zig, tj-loves-ocaml", headers.get("set-person").unwrap());
+        assert_eq!(vec!["lane-loves-go", "prime-loves-zig", "tj-loves-ocaml"], headers.get_all("set-person").collect::<Vec<_>>());
+        assert_eq!(Some("lane-loves-go"), headers.get_str("set-person"));
         assert!(!done3);
     }
-    
+
+    #[test]
+    fn test_order_and_case_preserved() {
+        let mut headers = Headers::new();
+        headers.parse(b"X-Trace-Id: 1\r\n").unwrap();
+        headers.parse(b"Set-Cookie: a=1; Path=/\r\n").unwrap();
+        headers.parse(b"set-cookie: b=2, c=3\r\n").unwrap();
+
+        assert_eq!(Some("1"), headers.get_str("x-trace-id"));
+        assert_eq!(vec!["a=1; Path=/", "b=2, c=3"], headers.get_all("SET-COOKIE").collect::<Vec<_>>());
+        assert_eq!(
+            vec![("X-Trace-Id", "1"), ("Set-Cookie", "a=1; Path=/"), ("set-cookie", "b=2, c=3")],
+            headers.iter().map(|(k, v)| (k.as_str(), v.to_str().unwrap())).collect::<Vec<_>>()
+        );
+        assert_eq!("X-Trace-Id: 1\r\nSet-Cookie: a=1; Path=/\r\nset-cookie: b=2, c=3", headers.to_string());
+    }
+
+    #[test]
+    fn test_set_replaces_all_values() {
+        let mut headers = Headers::new();
+        headers.append("Vary".to_string(), "Accept".to_string());
+        headers.append("Content-Type".to_string(), "text/plain".to_string());
+        headers.append("vary".to_string(), "Origin".to_string());
+
+        headers.set("VARY".to_string(), "*".to_string());
+        assert_eq!(vec![("VARY", "*"), ("Content-Type", "text/plain")], headers.iter().map(|(k, v)| (k.as_str(), v.to_str().unwrap())).collect::<Vec<_>>());
+
+        headers.remove_entry("content-type");
+        assert_eq!(1, headers.len());
+    }
+
+    #[test]
+    fn test_header_name_is_case_insensitive() {
+        assert_eq!(HeaderName::CONTENT_LENGTH, HeaderName::from("content-length"));
+        assert!("Bad Name".parse::<HeaderName>().is_err());
+
+        let mut headers = Headers::new();
+        headers.insert("content-length".to_string(), "1".to_string());
+        headers.insert(HeaderName::CONTENT_LENGTH, "2".to_string());
+        headers.insert("CONTENT-LENGTH", "3".to_string());
+        assert_eq!(1, headers.len());
+        assert_eq!(Some("3"), headers.get_str(HeaderName::CONTENT_LENGTH));
+        assert_eq!("CONTENT-LENGTH: 3", headers.to_string());
+
+        headers.remove_entry("Content-Length");
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn test_obs_text_kept_byte_exact() {
+        let mut headers = Headers::new();
+        headers.parse(b"X-Name: caf\xe9 \xff\r\n").unwrap();
+
+        let value = headers.get("x-name").unwrap();
+        assert_eq!(b"caf\xe9 \xff", value.as_bytes());
+        assert!(value.to_str().is_err());
+        assert_eq!(None, headers.get_str("x-name"));
+        assert_eq!(b"X-Name: caf\xe9 \xff\r\n".to_vec(), headers.to_wire().unwrap());
+    }
+
+    #[test]
+    fn test_control_chars_in_value_rejected() {
+        for data in [&b"X-A: a\rb\r\n"[..], b"X-A: a\0b\r\n", b"X-A: a\x01b\r\n", b"X-A: a\x7fb\r\n", b"X-A: a\nb\r\n"] {
+            let result = Headers::new().parse(data);
+            let error = result.unwrap_err();
+            assert_eq!(error, ParseErrorKind::InvalidHeaderValue, "{:?}", data);
+            assert_eq!(Some(6), error.offset(), "{:?}", data);
+        }
+
+        let error = Headers::new().parse(b"X-A:  abc\x01\r\n").unwrap_err();
+        assert_eq!(Some(9), error.offset());
+
+        let mut headers = Headers::new();
+        headers.parse(b"X-A: \ta\tb \r\n").unwrap();
+        assert_eq!("a\tb", headers.get("x-a").unwrap());
+    }
+
+    #[test]
+    fn test_to_wire_rejects_injected_lines() {
+        let mut headers = Headers::new();
+        headers.insert("X-A", "ok\r\nX-Injected: 1");
+        assert!(headers.to_wire().is_err());
+
+        let mut headers = Headers::new();
+        headers.insert("X-A: 1\r\nX-Injected".to_string(), "1");
+        assert!(headers.to_wire().is_err());
+
+        for name in ["", "X A", "X-A:"] {
+            let mut headers = Headers::new();
+            headers.insert(name, "1");
+            assert!(headers.to_wire().is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_contains_token_across_fields() {
+        let mut headers = Headers::new();
+        headers.append("Connection".to_string(), "upgrade".to_string());
+        headers.append("Connection".to_string(), "Close".to_string());
+        assert!(headers.contains_token("connection", "close"));
+        assert!(!headers.contains_token("connection", "keep-alive"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+use crate::http::headers::Headers;
+use crate::http::request::{ParseError, ParseErrorKind};
+
+/// Request method as defined by RFC 9110 section 9. Anything else that is a
+/// valid token is kept as an `Extension`; methods are case-sensitive, so
+/// `get` is an extension method rather than `GET`.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub enum Method {
+    #[default]
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Patch,
+    Options,
+    Trace,
+    Connect,
+    Extension(String),
+}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Patch => "PATCH",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Connect => "CONNECT",
+            Method::Extension(s) => s,
+        }
+    }
+
+    /// Safe methods are read-only from the client's point of view (RFC 9110 section 9.2.1).
+    pub fn is_safe(&self) -> bool {
+        matches!(self, Method::Get | Method::Head | Method::Options | Method::Trace)
+    }
+
+    /// Idempotent methods can be repeated with the same effect as sending them
+    /// once (RFC 9110 section 9.2.2).
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() || matches!(self, Method::Put | Method::Delete)
+    }
+}
+
+impl std::str::FromStr for Method {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let method = match s {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "PATCH" => Method::Patch,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "CONNECT" => Method::Connect,
+            _ => {
+                if s.is_empty() || !s.chars().all(Headers::is_valid_tchar) {
+                    return Err(ParseErrorKind::InvalidMethod.into());
+                }
+                Method::Extension(s.to_string())
+            },
+        };
+        Ok(method)
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_standard_methods() {
+        assert_eq!(Method::Get, "GET".parse().unwrap());
+        assert_eq!(Method::Connect, "CONNECT".parse().unwrap());
+        assert_eq!("PATCH", Method::Patch.to_string());
+    }
+
+    #[test]
+    fn test_extension_method() {
+        assert_eq!(Method::Extension("PROPFIND".to_string()), "PROPFIND".parse().unwrap());
+        // methods are case-sensitive
+        assert_eq!(Method::Extension("get".to_string()), "get".parse().unwrap());
+        assert_eq!("M-SEARCH", "M-SEARCH".parse::<Method>().unwrap().as_str());
+    }
+
+    #[test]
+    fn test_invalid_method() {
+        assert!("".parse::<Method>().is_err());
+        assert!("GE(T".parse::<Method>().is_err());
+        assert!("G@T".parse::<Method>().is_err());
+    }
+
+    #[test]
+    fn test_safe_and_idempotent() {
+        assert!(Method::Get.is_safe());
+        assert!(Method::Head.is_idempotent());
+        assert!(!Method::Put.is_safe());
+        assert!(Method::Put.is_idempotent());
+        assert!(Method::Delete.is_idempotent());
+        assert!(!Method::Post.is_idempotent());
+        assert!(!Method::Patch.is_idempotent());
+        assert!(!Method::Extension("PROPFIND".to_string()).is_safe());
+    }
+}
@@ -1,6 +1,21 @@
+pub mod body;
+pub mod extensions;
 pub mod request;
 pub mod headers;
+pub mod method;
 pub mod response;
+pub mod server;
+pub mod router;
+pub mod target;
+pub mod typed_headers;
+pub mod version;
 
-pub use request::{Request};
-pub use response::{Response};
\ No newline at end of file
+pub use body::Body;
+pub use method::Method;
+pub use extensions::Extensions;
+pub use request::{ParseError, ParseErrorKind, Parts, ParserLimits, Request, RequestReader};
+pub use response::{Response};
+pub use server::{Handler, Server, ServerConfig, ServerError};
+pub use router::Router;
+pub use target::{Query, RequestTarget, TargetForm};
+pub use version::Version;
\ No newline at end of file
@@ -1,37 +1,268 @@
+use bytes::Bytes;
+use futures_util::Stream;
 use tokio::io::{AsyncReadExt, AsyncRead};
 
+use std::collections::HashMap;
+
+use crate::http::extensions::Extensions;
 use crate::http::headers::Headers;
+use crate::http::method::Method;
+use crate::http::response::StatusCode;
+use crate::http::target::{RequestTarget, TargetForm};
+use crate::http::typed_headers::{ContentLength, TransferEncoding};
+use crate::http::version::Version;
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum ParseError {
+/// What kind of problem stopped a request from parsing; see `ParseError`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseErrorKind {
+    /// The request line isn't `method SP target SP version`.
+    InvalidRequestLine,
+    InvalidMethod,
+    InvalidTarget,
+    /// The version isn't `HTTP/` followed by a version number.
+    InvalidVersion,
+    /// A well-formed version other than HTTP/1.0 or HTTP/1.1.
+    UnsupportedVersion,
+    InvalidHeaderName,
+    InvalidHeaderValue,
+    /// A header line without a colon, or with whitespace in or around the name.
+    InvalidHeaderLine,
+    InvalidContentLength,
+    ConflictingContentLength,
+    TransferEncodingWithContentLength,
+    UnsupportedTransferEncoding(String),
+    /// Chunked framing that doesn't follow the grammar.
+    InvalidChunk,
+    RequestLineTooLong,
+    /// A chunk-size line, extensions included, longer than the configured limit.
+    ChunkLineTooLong,
+    HeadersTooLarge,
+    BodyTooLarge,
+    /// The stream ended partway through a request.
+    UnexpectedEof,
+    /// The stream ended before another request began.
+    ConnectionClosed,
+    /// Reading the stream failed; `ParseError::io_error` has the cause.
+    Io,
+    /// Any other violation of the grammar.
     InvalidFormat(String),
-    IOError,
 }
 
-impl std::fmt::Display for ParseError {
+impl std::fmt::Display for ParseErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::InvalidFormat(s) => write!(f, "Invalid request line format: {}",s),
-            ParseError::IOError => write!(f, "Read/write error on the io end"),
+            ParseErrorKind::InvalidRequestLine => write!(f, "Malformed request line"),
+            ParseErrorKind::InvalidMethod => write!(f, "Method is not a valid token"),
+            ParseErrorKind::InvalidTarget => write!(f, "Invalid request target"),
+            ParseErrorKind::InvalidVersion => write!(f, "Invalid protocol version"),
+            ParseErrorKind::UnsupportedVersion => write!(f, "Unsupported HTTP version"),
+            ParseErrorKind::InvalidHeaderName => write!(f, "Invalid chars within field name"),
+            ParseErrorKind::InvalidHeaderValue => write!(f, "Invalid chars within field value"),
+            ParseErrorKind::InvalidHeaderLine => write!(f, "Malformed header line"),
+            ParseErrorKind::InvalidContentLength => write!(f, "Invalid content-length"),
+            ParseErrorKind::ConflictingContentLength => write!(f, "Conflicting content-length values"),
+            ParseErrorKind::TransferEncodingWithContentLength => write!(f, "Both transfer-encoding and content-length present"),
+            ParseErrorKind::UnsupportedTransferEncoding(s) => write!(f, "Unsupported transfer-encoding: {}", s),
+            ParseErrorKind::InvalidChunk => write!(f, "Invalid chunked framing"),
+            ParseErrorKind::RequestLineTooLong => write!(f, "Request line exceeds the configured limit"),
+            ParseErrorKind::ChunkLineTooLong => write!(f, "Chunk-size line exceeds the configured limit"),
+            ParseErrorKind::HeadersTooLarge => write!(f, "Header section exceeds the configured limit"),
+            ParseErrorKind::BodyTooLarge => write!(f, "Body exceeds the configured limit"),
+            ParseErrorKind::UnexpectedEof => write!(f, "Connection closed partway through a request"),
+            ParseErrorKind::ConnectionClosed => write!(f, "Connection closed before a request was received"),
+            ParseErrorKind::Io => write!(f, "Read error on the io end"),
+            ParseErrorKind::InvalidFormat(s) => write!(f, "Invalid request format: {}", s),
         }
     }
 }
 
-impl std::error::Error for ParseError {}
+/// Why a request couldn't be parsed, where in it, and for read failures the
+/// underlying `io::Error`.
+#[derive(Debug)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    offset: Option<usize>,
+    source: Option<std::io::Error>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind) -> Self {
+        ParseError { kind, offset: None, source: None }
+    }
+
+    pub(crate) fn io(error: std::io::Error) -> Self {
+        ParseError { kind: ParseErrorKind::Io, offset: None, source: Some(error) }
+    }
+
+    /// Records where the error is, relative to the data being parsed.
+    pub(crate) fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Moves the offset along by `by` bytes, once the caller knows where the
+    /// data it handed a sub-parser started.
+    pub(crate) fn shifted(mut self, by: usize) -> Self {
+        self.offset = Some(self.offset.unwrap_or(0) + by);
+        self
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Byte offset into the request at which parsing failed, when known.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The read error behind an `Io` error.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        self.source.as_ref()
+    }
+
+    /// The status to answer the request with. `ConnectionClosed` and `Io`
+    /// errors leave no connection to answer on; they map to 400 like any
+    /// other malformed request.
+    pub fn status_code(&self) -> StatusCode {
+        match self.kind {
+            ParseErrorKind::RequestLineTooLong => StatusCode::StatusUriTooLong,
+            ParseErrorKind::HeadersTooLarge => StatusCode::StatusRequestHeaderFieldsTooLarge,
+            ParseErrorKind::BodyTooLarge => StatusCode::StatusContentTooLarge,
+            ParseErrorKind::UnsupportedVersion => StatusCode::StatusHttpVersionNotSupported,
+            _ => StatusCode::StatusBadRequest,
+        }
+    }
+}
+
+impl From<ParseErrorKind> for ParseError {
+    fn from(kind: ParseErrorKind) -> Self {
+        ParseError::new(kind)
+    }
+}
+
+impl PartialEq<ParseErrorKind> for ParseError {
+    fn eq(&self, kind: &ParseErrorKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+/// `io::Error` isn't `Clone`, so a clone carries a copy of its kind and message.
+impl Clone for ParseError {
+    fn clone(&self) -> Self {
+        ParseError {
+            kind: self.kind.clone(),
+            offset: self.offset,
+            source: self.source.as_ref().map(|e| std::io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Read failures come back as themselves; anything else is `InvalidData`
+/// wrapping the `ParseError`.
+impl From<ParseError> for std::io::Error {
+    fn from(error: ParseError) -> Self {
+        match error.source {
+            Some(source) => source,
+            None => std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+/// Upper bounds on what the parser will buffer for a single request.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ParserLimits {
+    /// Longest accepted request line, excluding the CRLF.
+    pub max_request_line_len: usize,
+    /// Total bytes across all header lines (trailers count too).
+    pub max_header_bytes: usize,
+    /// Number of header lines (trailers count too).
+    pub max_header_count: usize,
+    /// Largest accepted body, however it is framed.
+    pub max_body_size: usize,
+    /// Longest accepted chunk-size line, extensions included, excluding the CRLF.
+    pub max_chunk_line_len: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        ParserLimits {
+            max_request_line_len: 8 * 1024,
+            max_header_bytes: 64 * 1024,
+            max_header_count: 100,
+            max_body_size: 10 * 1024 * 1024,
+            max_chunk_line_len: 4 * 1024,
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 enum ParserState {
     Initialized,
     ParsingHeaders,
     ParsingBody,
+    ParsingChunkSize,
+    ParsingChunkData,
+    ParsingChunkEnd,
+    ParsingTrailers,
     Done,
 }
 
+/// Type-erased connection a streamed request body is read from.
+pub type BoxedRead = Box<dyn AsyncRead + Send + Unpin>;
+
 pub struct Request {
     request_line: RequestLine,
     headers: Headers,
     body: Vec<u8>,
+    /// Body bytes decoded so far, including any already handed out by
+    /// `next_body_chunk`.
+    body_received: usize,
+    body_source: Option<RequestReader<BoxedRead>>,
+    /// Why a streamed body stopped decoding; the stream can't be resumed.
+    body_error: Option<ParseError>,
+    trailers: Headers,
     parser_state: ParserState,
+    /// Bytes of the request consumed so far, for error offsets.
+    bytes_parsed: usize,
+    chunk_remaining: usize,
+    limits: ParserLimits,
+    header_bytes: usize,
+    header_count: usize,
+    params: HashMap<String, String>,
+    extensions: Extensions,
+}
+
+/// Everything about a request except its body, from `Request::into_parts`.
+#[derive(Debug, Default)]
+pub struct Parts {
+    pub method: Method,
+    pub target: RequestTarget,
+    pub version: Version,
+    pub headers: Headers,
+    pub trailers: Headers,
+    /// Path parameters captured by the `Router`.
+    pub params: HashMap<String, String>,
+    pub extensions: Extensions,
 }
 
 impl std::fmt::Display for Request {
@@ -40,25 +271,274 @@ impl std::fmt::Display for Request {
     }
 }
 
+impl Default for Request {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Request {
     pub fn new() -> Self {
+        Self::with_limits(ParserLimits::default())
+    }
+
+    pub fn with_limits(limits: ParserLimits) -> Self {
         Request {
             request_line: RequestLine::default(),
             headers: Headers::new(),
             body: Vec::new(),
+            body_received: 0,
+            body_source: None,
+            body_error: None,
+            trailers: Headers::new(),
             parser_state: ParserState::Initialized,
+            bytes_parsed: 0,
+            chunk_remaining: 0,
+            limits,
+            header_bytes: 0,
+            header_count: 0,
+            params: HashMap::new(),
+            extensions: Extensions::new(),
         }
     }
 
     pub fn get_target(&self) -> &str {
+        self.request_line.request_target.raw()
+    }
+
+    pub fn version(&self) -> Version {
+        self.request_line.http_version
+    }
+
+    pub fn target(&self) -> &RequestTarget {
         &self.request_line.request_target
     }
 
+    pub fn method(&self) -> &Method {
+        &self.request_line.method
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    /// The body read so far. Unless it is streamed (see `next_body_chunk`),
+    /// that is the whole body by the time a handler runs.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Typed data attached to the request, e.g. by middleware for the
+    /// handlers behind it.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Splits the request into its parts and body. A streamed body has to be
+    /// read with `next_body_chunk` first; only what was already read is kept.
+    pub fn into_parts(self) -> (Parts, Vec<u8>) {
+        let parts = Parts {
+            method: self.request_line.method,
+            target: self.request_line.request_target,
+            version: self.request_line.http_version,
+            headers: self.headers,
+            trailers: self.trailers,
+            params: self.params,
+            extensions: self.extensions,
+        };
+        (parts, self.body)
+    }
+
+    /// Puts a complete request back together, e.g. after `into_parts` or to
+    /// build one for a test.
+    pub fn from_parts(parts: Parts, body: Vec<u8>) -> Self {
+        Request {
+            request_line: RequestLine::build(parts.version, parts.target, parts.method),
+            headers: parts.headers,
+            body_received: body.len(),
+            body,
+            trailers: parts.trailers,
+            parser_state: ParserState::Done,
+            params: parts.params,
+            extensions: parts.extensions,
+            ..Request::new()
+        }
+    }
+
+    /// Returns a path parameter captured by the `Router`, e.g. `id` for `/users/:id`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|s| s.as_str())
+    }
+
+    /// Trailer fields sent after a chunked request body.
+    pub fn trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    /// Whether the client is willing to reuse the connection for another request.
+    pub fn keep_alive(&self) -> bool {
+        if self.headers.contains_token("connection", "close") {
+            false
+        } else if self.headers.contains_token("connection", "keep-alive") {
+            true
+        } else {
+            self.version().keep_alive_by_default()
+        }
+    }
+
+    /// Whether the client sent `TE: trailers`, i.e. will accept trailer fields
+    /// on a chunked response.
+    pub fn accepts_trailers(&self) -> bool {
+        self.headers.contains_token("te", "trailers")
+    }
+
+    /// Whether the client sent `Expect: 100-continue` and is waiting for a
+    /// `100 Continue` before sending the body. HTTP/1.0 expectations are ignored.
+    pub fn expects_continue(&self) -> bool {
+        self.version() >= Version::Http11 && self.headers.contains_token("expect", "100-continue")
+    }
+
+    /// The first `Expect` value other than `100-continue`; the server can't
+    /// meet it and answers `417 Expectation Failed`.
+    pub(crate) fn unsupported_expectation(&self) -> Option<&str> {
+        if self.version() < Version::Http11 {
+            return None;
+        }
+        self.headers.get_all("expect")
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|v| v.trim())
+            .find(|v| !v.is_empty() && !v.eq_ignore_ascii_case("100-continue"))
+    }
+
+    /// Whether the whole request, body included, has been read.
+    pub fn is_complete(&self) -> bool {
+        self.parser_state == ParserState::Done
+    }
+
+    /// The next piece of the body, or `None` once it has all been read.
+    ///
+    /// Whatever has been read already comes first. For a request whose body
+    /// is streamed (`streaming_request_from_reader` or
+    /// `ServerConfig::stream_request_bodies`) the rest is then read from the
+    /// connection and decoded as it is asked for, so it never has to fit in
+    /// memory at once. Trailers are available once this returns `None`.
+    pub async fn next_body_chunk(&mut self) -> Result<Option<Bytes>, std::io::Error> {
+        loop {
+            if !self.body.is_empty() {
+                return Ok(Some(Bytes::from(std::mem::take(&mut self.body))));
+            }
+            if self.parser_state == ParserState::Done {
+                return Ok(None);
+            }
+            if let Some(e) = self.body_error.clone() {
+                return Err(e.into());
+            }
+            let Some(mut source) = self.body_source.take() else {
+                return Ok(None);
+            };
+            let result = source.read_body_chunk(self).await;
+            self.body_source = Some(source);
+            self.body_error = result.err();
+        }
+    }
+
+    /// The body as a stream of pieces; see `next_body_chunk`.
+    pub fn body_stream(&mut self) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send + '_ {
+        futures_util::stream::try_unfold(self, |request| async move {
+            Ok(request.next_body_chunk().await?.map(|chunk| (chunk, request)))
+        })
+    }
+
+    /// Hands the request the connection the rest of its body is read from.
+    pub(crate) fn set_body_source(&mut self, source: RequestReader<BoxedRead>) {
+        self.body_source = Some(source);
+    }
+
+    pub(crate) fn take_body_source(&mut self) -> Option<RequestReader<BoxedRead>> {
+        self.body_source.take()
+    }
+
+    pub(crate) fn set_params(&mut self, params: HashMap<String, String>) {
+        self.params = params;
+    }
+
+    /// Parses `Content-Length`, accepting a repeated field only when every value agrees.
+    fn content_length(&self) -> Result<Option<usize>, ParseError> {
+        Ok(self.headers.typed_try_get::<ContentLength>()?.map(|ContentLength(n)| n))
+    }
+
+    /// Works out how the body is delimited once the headers are in, following
+    /// RFC 9112 section 6.3. Ambiguous framing is rejected outright rather than
+    /// guessed at, since a proxy in front of us may have guessed differently.
+    fn body_framing(&self) -> Result<ParserState, ParseError> {
+        if self.headers.contains_key("transfer-encoding") {
+            if !self.version().supports_chunked() {
+                return Err(ParseErrorKind::InvalidFormat("transfer-encoding is not allowed in HTTP/1.0".to_string()).into());
+            }
+            if self.headers.contains_key("content-length") {
+                return Err(ParseErrorKind::TransferEncodingWithContentLength.into());
+            }
+
+            // repeated fields are one list, so "chunked" twice across lines is still a double coding
+            let TransferEncoding(codings) = self.headers.typed_try_get()?.unwrap_or(TransferEncoding(Vec::new()));
+
+            // chunked is the only coding we can decode, and it must be applied exactly once
+            if let Some(unknown) = codings.iter().find(|c| *c != "chunked") {
+                return Err(ParseErrorKind::UnsupportedTransferEncoding(unknown.to_string()).into());
+            }
+            if codings.len() != 1 {
+                return Err(ParseErrorKind::UnsupportedTransferEncoding(codings.join(", ")).into());
+            }
+            return Ok(ParserState::ParsingChunkSize);
+        }
+
+        match self.content_length()? {
+            Some(n) if n > self.limits.max_body_size => Err(ParseErrorKind::BodyTooLarge.into()),
+            Some(0) | None => Ok(ParserState::Done),
+            Some(_) => Ok(ParserState::ParsingBody),
+        }
+    }
+
+    /// Accounts a parsed header or trailer line against the limits. `bytes_read`
+    /// of 0 means no complete line was available in `data` yet.
+    fn check_field_limits(&mut self, data: &[u8], bytes_read: usize, done: bool) -> Result<(), ParseError> {
+        if bytes_read == 0 {
+            if self.header_bytes + data.len() > self.limits.max_header_bytes {
+                return Err(ParseErrorKind::HeadersTooLarge.into());
+            }
+            return Ok(());
+        }
+
+        self.header_bytes += bytes_read;
+        if !done {
+            self.header_count += 1;
+        }
+        if self.header_bytes > self.limits.max_header_bytes || self.header_count > self.limits.max_header_count {
+            return Err(ParseErrorKind::HeadersTooLarge.into());
+        }
+        Ok(())
+    }
+
     fn parse_single(&mut self, data: &[u8]) -> Result<usize, ParseError> {
 
         match self.parser_state {
             ParserState::Initialized => {
                 match RequestLine::parse(data) {
+                    Ok((Some(_), bytes_read)) if bytes_read - 2 > self.limits.max_request_line_len => {
+                        Err(ParseErrorKind::RequestLineTooLong.into())
+                    },
+                    Ok((None, _)) if data.len() > self.limits.max_request_line_len + 1 => {
+                        Err(ParseErrorKind::RequestLineTooLong.into())
+                    },
                     Ok((Some(request_line), bytes_read)) => {
                         self.request_line = request_line;
                         self.parser_state = ParserState::ParsingHeaders;
@@ -73,20 +553,9 @@ impl Request {
             ParserState::ParsingHeaders => {
                 match self.headers.parse(data) {
                     Ok((bytes_read, done)) => {
+                        self.check_field_limits(data, bytes_read, done)?;
                         if done {
-                            match self.headers.get("content-length") {
-                                Some(str) => {
-                                    match str.parse::<usize>() {
-                                        Ok(0) => { self.parser_state = ParserState::Done },
-                                        Ok(_) => { self.parser_state = ParserState::ParsingBody },
-                                        Err(_) => { return Err(ParseError::InvalidFormat("invalid content-length".to_string())); },
-                                    }
-                                },
-                                None => {
-                                    // no content-length, assume there's no body to parse
-                                    self.parser_state = ParserState::Done;
-                                }
-                            }
+                            self.parser_state = self.body_framing()?;
                         }
                         Ok(bytes_read)
                     },
@@ -94,26 +563,90 @@ impl Request {
                 }
             },
             ParserState::ParsingBody => {
-                let content_length = self.headers.get("content-length")
-                    .and_then(|s| s.parse::<usize>().ok())
-                    .unwrap_or(0);
+                let content_length = self.content_length()?.unwrap_or(0);
 
-                let bytes_needed = content_length - self.body.len();
+                let bytes_needed = content_length - self.body_received;
                 let bytes_to_consume = bytes_needed.min(data.len());
 
                 // append data to body
                 self.body.extend_from_slice(&data[..bytes_to_consume]);
+                self.body_received += bytes_to_consume;
 
-                if self.body.len() > content_length {
-                    return Err(ParseError::InvalidFormat("body longer than content-length".to_string()));
-                } else if self.body.len() == content_length {
+                if self.body_received > content_length {
+                    return Err(ParseErrorKind::InvalidFormat("body longer than content-length".to_string()).into());
+                } else if self.body_received == content_length {
                     self.parser_state = ParserState::Done;
                 }
 
                 Ok(bytes_to_consume)
             },
+            ParserState::ParsingChunkSize => {
+                let Some(line_end) = data.windows(2).position(|w| w == b"\r\n") else {
+                    if data.len() > self.limits.max_chunk_line_len + 1 {
+                        return Err(ParseErrorKind::ChunkLineTooLong.into());
+                    }
+                    return Ok(0);
+                };
+                if line_end > self.limits.max_chunk_line_len {
+                    return Err(ParseErrorKind::ChunkLineTooLong.into());
+                }
+
+                let line = String::from_utf8_lossy(&data[..line_end]);
+                // chunk extensions carry no meaning for us, drop them
+                let size_str = line.split(';').next().unwrap_or_default().trim();
+
+                if size_str.is_empty() || !size_str.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(ParseErrorKind::InvalidChunk.into());
+                }
+                let chunk_size = usize::from_str_radix(size_str, 16)
+                    .map_err(|_| ParseErrorKind::InvalidChunk)?;
+
+                if self.body_received.saturating_add(chunk_size) > self.limits.max_body_size {
+                    return Err(ParseErrorKind::BodyTooLarge.into());
+                }
+
+                if chunk_size == 0 {
+                    self.parser_state = ParserState::ParsingTrailers;
+                } else {
+                    self.chunk_remaining = chunk_size;
+                    self.parser_state = ParserState::ParsingChunkData;
+                }
+
+                Ok(line_end+2)
+            },
+            ParserState::ParsingChunkData => {
+                let bytes_to_consume = self.chunk_remaining.min(data.len());
+
+                self.body.extend_from_slice(&data[..bytes_to_consume]);
+                self.body_received += bytes_to_consume;
+                self.chunk_remaining -= bytes_to_consume;
+
+                if self.chunk_remaining == 0 {
+                    self.parser_state = ParserState::ParsingChunkEnd;
+                }
+
+                Ok(bytes_to_consume)
+            },
+            ParserState::ParsingChunkEnd => {
+                if data.len() < 2 {
+                    return Ok(0);
+                }
+                if &data[..2] != b"\r\n" {
+                    return Err(ParseErrorKind::InvalidChunk.into());
+                }
+                self.parser_state = ParserState::ParsingChunkSize;
+                Ok(2)
+            },
+            ParserState::ParsingTrailers => {
+                let (bytes_read, done) = self.trailers.parse(data)?;
+                self.check_field_limits(data, bytes_read, done)?;
+                if done {
+                    self.parser_state = ParserState::Done;
+                }
+                Ok(bytes_read)
+            },
             ParserState::Done => {
-                Err(ParseError::InvalidFormat("attempting to parse in a done state".to_string()))
+                Err(ParseErrorKind::InvalidFormat("attempting to parse in a done state".to_string()).into())
             }
         }
 
@@ -124,7 +657,8 @@ impl Request {
 
         while self.parser_state != ParserState::Done && total_bytes_parsed < data.len() {
             let remaining_data = &data[total_bytes_parsed..];
-            let bytes_read = self.parse_single(remaining_data)?;
+            let bytes_read = self.parse_single(remaining_data)
+                .map_err(|e| e.shifted(self.bytes_parsed + total_bytes_parsed))?;
 
             if bytes_read == 0 {
                 break;
@@ -132,22 +666,23 @@ impl Request {
 
             total_bytes_parsed += bytes_read;
         }
-        
+        self.bytes_parsed += total_bytes_parsed;
+
         Ok(total_bytes_parsed)
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Default)]
 pub struct RequestLine {
-    http_version: String,
-    request_target: String,
-    method: String,
+    http_version: Version,
+    request_target: RequestTarget,
+    method: Method,
 }
 
 impl std::fmt::Display for RequestLine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Request line:\n- Method: {}\n- Target: {}\n- Version: {}",
-            self.method, self.request_target, self.http_version)
+            self.method, self.request_target, self.http_version.as_str())
     }
 }
 
@@ -155,42 +690,46 @@ impl TryFrom<&str> for RequestLine {
     type Error = ParseError;
 
     fn try_from(line: &str) -> Result<Self, Self::Error> {
+        // errors point at the part of the line that failed
+        let offset_of = |part: &str| part.as_ptr() as usize - line.as_ptr() as usize;
+
         let parts: Vec<&str> = line.split_whitespace().collect();
         match parts.as_slice() {
             [method, target, version] => {
-                for char in method.chars() {
-                    if !char.is_alphabetic() {
-                        return Err(ParseError::InvalidFormat("method contains non-alphabetic characters".to_string()));
-                    }
+                let method = method.parse::<Method>()?;
+                let request_target = target.parse::<RequestTarget>()
+                    .map_err(|e| e.at(offset_of(target)))?;
+
+                // authority-form belongs to CONNECT and asterisk-form to OPTIONS, exclusively
+                let form = request_target.form();
+                if (form == TargetForm::Authority) != (method == Method::Connect)
+                    || (form == TargetForm::Asterisk && method != Method::Options) {
+                    return Err(ParseError::new(ParseErrorKind::InvalidTarget).at(offset_of(target)));
                 }
-                match version.split('/').collect::<Vec<_>>().as_slice() {
-                    [http_part, version] => {
-                        if http_part != &"HTTP" {
-                            return Err(ParseError::InvalidFormat("unrecognized protocol".to_string()));
-                        }
-                        if version != &"1.1" {
-                            return Err(ParseError::InvalidFormat("unrecognized http version".to_string()));
-                        }
+                match version.split_once('/') {
+                    Some(("HTTP", number)) => {
+                        let http_version = number.parse::<Version>()
+                            .map_err(|e| e.at(offset_of(number)))?;
                         Ok(RequestLine {
-                            http_version: version.to_string(),
-                            request_target: target.to_string(),
-                            method: method.to_string(),
+                            http_version,
+                            request_target,
+                            method,
                         })
                     },
-                    _ => Err(ParseError::InvalidFormat("malformed request line".to_string())),
+                    _ => Err(ParseError::new(ParseErrorKind::InvalidVersion).at(offset_of(version))),
                 }
             },
-            _ => Err(ParseError::InvalidFormat("malformed request line".to_string())),
+            _ => Err(ParseErrorKind::InvalidRequestLine.into()),
         }
     }
 }
 
 impl RequestLine {
-    pub fn build(http_version: &str, request_target: &str, method: &str) -> Self {
+    pub fn build(http_version: Version, request_target: RequestTarget, method: Method) -> Self {
         RequestLine {
-            http_version: http_version.to_string(),
-            request_target: request_target.to_string(),
-            method: method.to_string(),
+            http_version,
+            request_target,
+            method,
         }
     }
 
@@ -207,47 +746,156 @@ impl RequestLine {
 
 const BUFFER_SIZE: usize = 8;
 
-pub async fn request_from_reader<R>(mut reader: R) -> Result<Request, ParseError>
+/// Free buffer space to read into while streaming a body, so large uploads
+/// aren't pulled in a few bytes at a time.
+const BODY_READ_SIZE: usize = 8 * 1024;
+
+/// Reads consecutive requests off one connection.
+///
+/// Bytes read past the end of a request stay buffered for the next call, so
+/// pipelined requests arriving in the same packet are parsed in order.
+pub struct RequestReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    read_to_index: usize,
+    limits: ParserLimits,
+}
+
+impl<R> RequestReader<R>
     where R: AsyncRead + Unpin
 {
-    let mut buf = vec![0u8; BUFFER_SIZE];
-    let mut read_to_index = 0;
-    let mut request = Request::new();
+    pub fn new(reader: R) -> Self {
+        Self::with_limits(reader, ParserLimits::default())
+    }
 
-    while request.parser_state != ParserState::Done {
-        // grow the buffer as required
-        if read_to_index >= buf.len() {
-            buf.resize(buf.len()*2, 0);
+    pub fn with_limits(reader: R, limits: ParserLimits) -> Self {
+        RequestReader {
+            reader,
+            buf: vec![0u8; BUFFER_SIZE],
+            read_to_index: 0,
+            limits,
         }
+    }
 
-        // read from the reader into the buffer
-        let bytes_read = reader.read(&mut buf[read_to_index..]).await
-            .map_err(|_| ParseError::IOError)?;
+    /// Bytes already read from the stream but not yet parsed.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[..self.read_to_index]
+    }
 
-        if bytes_read == 0 {
-            break;
-        }
-        read_to_index += bytes_read;
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub async fn next_request(&mut self) -> Result<Request, ParseError> {
+        let mut request = self.next_request_head().await?;
+        self.read_body(&mut request).await?;
+        Ok(request)
+    }
+
+    /// Reads the next request up to the end of its header section, so it can
+    /// be looked at (e.g. to answer `Expect: 100-continue`) before the body is
+    /// read. Body bytes that arrived along with the headers are parsed too;
+    /// `read_body` reads the rest.
+    pub async fn next_request_head(&mut self) -> Result<Request, ParseError> {
+        let mut request = Request::with_limits(self.limits);
+        self.read_until(&mut request, |state| !matches!(state, ParserState::Initialized | ParserState::ParsingHeaders)).await?;
+        Ok(request)
+    }
+
+    /// Reads the rest of a request returned by `next_request_head`.
+    pub async fn read_body(&mut self, request: &mut Request) -> Result<(), ParseError> {
+        self.read_until(request, |state| state == ParserState::Done).await
+    }
+
+    /// Reads and decodes more of a streamed body into `request`.
+    async fn read_body_chunk(&mut self, request: &mut Request) -> Result<(), ParseError> {
+        self.fill(request.parser_state, BODY_READ_SIZE).await
+            .map_err(|e| e.shifted(request.bytes_parsed))?;
+        self.parse_buffered(request)
+    }
+
+    async fn read_until(&mut self, request: &mut Request, done: fn(ParserState) -> bool) -> Result<(), ParseError> {
+        loop {
+            // parse what we have thus far, including leftovers from the last request
+            self.parse_buffered(request)?;
+
+            if done(request.parser_state) {
+                return Ok(());
+            }
 
-        // parse what we have thus far
-        let num_bytes_parsed = request.parse(&buf[..read_to_index])?;
+            self.fill(request.parser_state, 1).await
+                .map_err(|e| e.shifted(request.bytes_parsed))?;
+        }
+    }
 
-        println!("Current buffer state: {:?}", buf);
-        println!("   As a string, that's: {}", String::from_utf8_lossy(&buf));
+    fn parse_buffered(&mut self, request: &mut Request) -> Result<(), ParseError> {
+        let num_bytes_parsed = request.parse(&self.buf[..self.read_to_index])?;
 
         // slide the buffer left to remove the parsed bytes
         if num_bytes_parsed > 0 {
-            buf.copy_within(num_bytes_parsed..read_to_index, 0);
-            read_to_index -= num_bytes_parsed;
+            self.buf.copy_within(num_bytes_parsed..self.read_to_index, 0);
+            self.read_to_index -= num_bytes_parsed;
+        }
+        Ok(())
+    }
+
+    /// Reads once from the stream, first growing the buffer until it has at
+    /// least `min_free` bytes of room. Error offsets are relative to the
+    /// unparsed data.
+    async fn fill(&mut self, state: ParserState, min_free: usize) -> Result<(), ParseError> {
+        while self.buf.len() - self.read_to_index < min_free {
+            self.buf.resize(self.buf.len()*2, 0);
         }
 
+        // read from the reader into the buffer
+        let bytes_read = self.reader.read(&mut self.buf[self.read_to_index..]).await
+            .map_err(|e| ParseError::io(e).at(self.read_to_index))?;
+
+        if bytes_read == 0 {
+            if state == ParserState::Initialized && self.read_to_index == 0 {
+                return Err(ParseErrorKind::ConnectionClosed.into());
+            }
+            return Err(ParseError::new(ParseErrorKind::UnexpectedEof).at(self.read_to_index));
+        }
+        self.read_to_index += bytes_read;
+        Ok(())
     }
+}
+
+pub async fn request_from_reader<R>(reader: R) -> Result<Request, ParseError>
+    where R: AsyncRead + Unpin
+{
+    RequestReader::new(reader).next_request().await
+}
+
+pub async fn request_from_reader_with_limits<R>(reader: R, limits: ParserLimits) -> Result<Request, ParseError>
+    where R: AsyncRead + Unpin
+{
+    RequestReader::with_limits(reader, limits).next_request().await
+}
+
+/// Like `request_from_reader`, but returns as soon as the headers are parsed.
+/// The body is read from `reader` as the request's `next_body_chunk` or
+/// `body_stream` asks for it.
+pub async fn streaming_request_from_reader<R>(reader: R) -> Result<Request, ParseError>
+    where R: AsyncRead + Send + Unpin + 'static
+{
+    streaming_request_from_reader_with_limits(reader, ParserLimits::default()).await
+}
+
+pub async fn streaming_request_from_reader_with_limits<R>(reader: R, limits: ParserLimits) -> Result<Request, ParseError>
+    where R: AsyncRead + Send + Unpin + 'static
+{
+    let mut reader = RequestReader::with_limits(Box::new(reader) as BoxedRead, limits);
+    let mut request = reader.next_request_head().await?;
+    request.set_body_source(reader);
     Ok(request)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use futures_util::TryStreamExt;
     use std::io::prelude::*;
     use std::io::Cursor;
 
@@ -257,9 +905,9 @@ mod test {
         let rl = RequestLine::try_from(line).unwrap();
         println!("{:?}", rl);
         let expected = RequestLine {
-            http_version: "1.1".to_string(),
-            request_target: "/".to_string(),
-            method: "GET".to_string(),
+            http_version: Version::Http11,
+            request_target: "/".parse().unwrap(),
+            method: Method::Get,
         };
         assert_eq!(expected, rl);
     }
@@ -271,9 +919,9 @@ mod test {
 
         let request = request_from_reader(reader).await.unwrap();
 
-        assert_eq!(request.request_line.method, "GET");
-        assert_eq!(request.request_line.request_target, "/coffee");
-        assert_eq!(request.request_line.http_version, "1.1");
+        assert_eq!(request.request_line.method, Method::Get);
+        assert_eq!(request.request_line.request_target.raw(), "/coffee");
+        assert_eq!(request.request_line.http_version, Version::Http11);
     }
 
     #[tokio::test]
@@ -283,7 +931,112 @@ mod test {
 
         let request = request_from_reader(reader).await;
 
-        assert_eq!(request.err(), Some(ParseError::InvalidFormat("malformed request line".to_string())));
+        assert_eq!(request.err().unwrap(), ParseErrorKind::InvalidRequestLine);
+    }
+
+    #[tokio::test]
+    async fn test_request_from_reader_eof() {
+        let request = request_from_reader(Cursor::new("")).await;
+        assert_eq!(request.err().unwrap(), ParseErrorKind::ConnectionClosed);
+
+        let request = request_from_reader(Cursor::new("GET / HTTP/1.1\r\nHost: local")).await;
+        let error = request.err().unwrap();
+        assert_eq!(error, ParseErrorKind::UnexpectedEof);
+        assert_eq!(Some(27), error.offset());
+    }
+
+    struct FailingReader;
+
+    impl AsyncRead for FailingReader {
+        fn poll_read(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>, _buf: &mut tokio::io::ReadBuf<'_>) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_io_error_is_kept() {
+        let error = request_from_reader(FailingReader).await.err().unwrap();
+        assert_eq!(error, ParseErrorKind::Io);
+        assert_eq!(Some(std::io::ErrorKind::ConnectionReset), error.io_error().map(|e| e.kind()));
+        assert!(std::error::Error::source(&error).is_some());
+        assert_eq!(std::io::ErrorKind::ConnectionReset, std::io::Error::from(error).kind());
+    }
+
+    #[test]
+    fn test_parse_error_offsets_and_status() {
+        let cases: [(&[u8], ParseErrorKind, usize, StatusCode); 6] = [
+            (b"GET / HTTP/1.1\r\nHost: a\r\nBad@Name: x\r\n\r\n", ParseErrorKind::InvalidHeaderName, 25, StatusCode::StatusBadRequest),
+            (b"GET / HTTP/1.1\r\nHost: a\r\nX-A: a\x01\r\n\r\n", ParseErrorKind::InvalidHeaderValue, 31, StatusCode::StatusBadRequest),
+            (b"GET / HTTP/2.0\r\n\r\n", ParseErrorKind::UnsupportedVersion, 11, StatusCode::StatusHttpVersionNotSupported),
+            (b"GET / FTP/1.1\r\n\r\n", ParseErrorKind::InvalidVersion, 6, StatusCode::StatusBadRequest),
+            (b"G@T / HTTP/1.1\r\n\r\n", ParseErrorKind::InvalidMethod, 0, StatusCode::StatusBadRequest),
+            (b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\nzz\r\n", ParseErrorKind::InvalidChunk, 57, StatusCode::StatusBadRequest),
+        ];
+
+        for (data, kind, offset, status) in cases {
+            let error = Request::new().parse(data).unwrap_err();
+            assert_eq!(error, kind, "{:?}", String::from_utf8_lossy(data));
+            assert_eq!(Some(offset), error.offset(), "{:?}", String::from_utf8_lossy(data));
+            assert_eq!(status, error.status_code());
+        }
+
+        let error = Request::with_limits(small_limits()).parse(b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n").unwrap_err();
+        assert_eq!(StatusCode::StatusContentTooLarge, error.status_code());
+        assert_eq!("Body exceeds the configured limit at byte 36", error.to_string());
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let mut request = Request::new();
+        request.parse(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        assert!(request.keep_alive());
+
+        let mut request = Request::new();
+        request.parse(b"GET / HTTP/1.1\r\nConnection: Close\r\n\r\n").unwrap();
+        assert!(!request.keep_alive());
+    }
+
+    #[test]
+    fn test_request_line_methods() {
+        let rl = RequestLine::try_from("DELETE /users/1 HTTP/1.1").unwrap();
+        assert_eq!(&Method::Delete, &rl.method);
+
+        let rl = RequestLine::try_from("PROPFIND /dav HTTP/1.1").unwrap();
+        assert_eq!(Method::Extension("PROPFIND".to_string()), rl.method);
+
+        assert!(RequestLine::try_from("G{T / HTTP/1.1").is_err());
+    }
+
+    #[test]
+    fn test_request_line_target_forms() {
+        let rl = RequestLine::try_from("GET /video?x=1 HTTP/1.1").unwrap();
+        assert_eq!("/video", rl.request_target.path());
+        assert_eq!(Some("1"), rl.request_target.query().get("x"));
+
+        assert!(RequestLine::try_from("CONNECT example.com:443 HTTP/1.1").is_ok());
+        assert!(RequestLine::try_from("OPTIONS * HTTP/1.1").is_ok());
+        assert!(RequestLine::try_from("GET example.com:443 HTTP/1.1").is_err());
+        assert!(RequestLine::try_from("CONNECT / HTTP/1.1").is_err());
+        assert!(RequestLine::try_from("GET * HTTP/1.1").is_err());
+    }
+
+    #[test]
+    fn test_http_10_request() {
+        let rl = RequestLine::try_from("GET / HTTP/1.0").unwrap();
+        assert_eq!(Version::Http10, rl.http_version);
+        assert!(RequestLine::try_from("GET / HTTP/2.0").is_err());
+
+        let mut request = Request::new();
+        request.parse(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        assert!(!request.keep_alive());
+
+        let mut request = Request::new();
+        request.parse(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n").unwrap();
+        assert!(request.keep_alive());
+
+        let mut request = Request::new();
+        let result = request.parse(b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n");
+        assert!(result.is_err());
     }
 
     #[test]
@@ -296,7 +1049,7 @@ mod test {
         
         // Check request line
         let rl = request.request_line;
-        assert_eq!(rl.method, "GET");
+        assert_eq!(rl.method, Method::Get);
         
         // Check headers
         assert_eq!("localhost:42069", request.headers.get("host").unwrap());
@@ -375,6 +1128,186 @@ mod test {
         assert!(request.body.is_empty()); // Body ignored without Content-Length
     }
 
+    #[test]
+    fn test_chunked_body() {
+        let mut request = Request::new();
+        let data = b"POST /submit HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+5\r\nhello\r\n7\r\n world!\r\n0\r\n\r\n";
+
+        let consumed = request.parse(data).unwrap();
+        assert_eq!(data.len(), consumed);
+        assert_eq!(request.parser_state, ParserState::Done);
+        assert_eq!("hello world!", String::from_utf8_lossy(&request.body));
+        assert!(request.trailers().is_empty());
+    }
+
+    #[test]
+    fn test_chunked_body_with_extensions_and_trailers() {
+        let mut request = Request::new();
+        let data = b"POST /submit HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+A;name=value\r\n0123456789\r\n0;last\r\nX-Checksum: abc123\r\nX-Count: 10\r\n\r\n";
+
+        request.parse(data).unwrap();
+        assert_eq!(request.parser_state, ParserState::Done);
+        assert_eq!("0123456789", String::from_utf8_lossy(&request.body));
+        assert_eq!("abc123", request.trailers().get("x-checksum").unwrap());
+        assert_eq!("10", request.trailers().get("x-count").unwrap());
+    }
+
+    #[test]
+    fn test_chunked_body_invalid_size() {
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n";
+
+        let result = request.parse(data);
+        assert_eq!(result.unwrap_err(), ParseErrorKind::InvalidChunk);
+    }
+
+    #[test]
+    fn test_chunked_body_missing_crlf() {
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabcX\r\n";
+
+        let result = request.parse(data);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_body_from_chunk_reader() {
+        let http_data = "POST /submit HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+4\r\nWiki\r\n6\r\npedia \r\nE\r\nin \r\n\r\nchunks.\r\n0\r\nX-Done: yes\r\n\r\n";
+
+        for bytes_per_read in [1, 2, 5, 1024] {
+            let request = request_from_reader(ChunkReader::new(http_data, bytes_per_read)).await.unwrap();
+            assert_eq!("Wikipedia in \r\n\r\nchunks.", String::from_utf8_lossy(&request.body));
+            assert_eq!("yes", request.trailers().get("x-done").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_identical_content_length() {
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 5\r\n\r\nhello";
+
+        request.parse(data).unwrap();
+        assert_eq!(request.parser_state, ParserState::Done);
+        assert_eq!(b"hello", request.body.as_slice());
+    }
+
+    #[test]
+    fn test_conflicting_content_length() {
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\nhello!";
+
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::ConflictingContentLength);
+    }
+
+    #[test]
+    fn test_invalid_content_length() {
+        for value in ["abc", "+5", "-1", "5 5", ""] {
+            let mut request = Request::new();
+            let data = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", value);
+            assert_eq!(request.parse(data.as_bytes()).unwrap_err(), ParseErrorKind::InvalidContentLength, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_content_length_with_transfer_encoding() {
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::TransferEncodingWithContentLength);
+    }
+
+    #[test]
+    fn test_unknown_transfer_coding() {
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::UnsupportedTransferEncoding("gzip".to_string()));
+
+        let mut request = Request::new();
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::UnsupportedTransferEncoding("chunked, chunked".to_string()));
+    }
+
+    fn small_limits() -> ParserLimits {
+        ParserLimits {
+            max_request_line_len: 20,
+            max_header_bytes: 40,
+            max_header_count: 2,
+            max_body_size: 8,
+            max_chunk_line_len: 8,
+        }
+    }
+
+    #[test]
+    fn test_request_line_too_long() {
+        let mut request = Request::with_limits(small_limits());
+        let data = b"GET /a-very-long-target-indeed HTTP/1.1\r\n\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::RequestLineTooLong);
+
+        // detected before the line is even complete
+        let mut request = Request::with_limits(small_limits());
+        let data = b"GET /a-very-long-target-indeed";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::RequestLineTooLong);
+    }
+
+    #[test]
+    fn test_too_many_headers() {
+        let mut request = Request::with_limits(small_limits());
+        let data = b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::HeadersTooLarge);
+    }
+
+    #[test]
+    fn test_header_bytes_too_large() {
+        let mut request = Request::with_limits(small_limits());
+        let data = b"GET / HTTP/1.1\r\nX-Big: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::HeadersTooLarge);
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        let mut request = Request::with_limits(small_limits());
+        let data = b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::BodyTooLarge);
+
+        let mut request = Request::with_limits(small_limits());
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n4\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::BodyTooLarge);
+    }
+
+    #[test]
+    fn test_chunk_line_too_long() {
+        let mut request = Request::with_limits(small_limits());
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1;ext=long\r\na\r\n0\r\n\r\n";
+        assert_eq!(request.parse(data).unwrap_err(), ParseErrorKind::ChunkLineTooLong);
+
+        // extensions within the limit are fine
+        let mut request = Request::with_limits(small_limits());
+        let data = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1;ext=1\r\na\r\n0\r\n\r\n";
+        request.parse(data).unwrap();
+        assert_eq!(b"a", request.body());
+    }
+
+    #[tokio::test]
+    async fn test_unterminated_chunk_line_is_not_buffered() {
+        // a chunk-size line that never ends must fail once it passes the
+        // limit, not grow the buffer until the stream runs out
+        let http_data = format!("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1;{}", "x".repeat(64 * 1024));
+        let result = request_from_reader_with_limits(ChunkReader::new(&http_data, 4), small_limits()).await;
+        let error = result.err().unwrap();
+        assert_eq!(error, ParseErrorKind::ChunkLineTooLong);
+        assert!(error.offset().unwrap() < 64);
+    }
+
+    #[tokio::test]
+    async fn test_request_from_reader_with_limits() {
+        let http_data = "GET / HTTP/1.1\r\nX-Big: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let result = request_from_reader_with_limits(ChunkReader::new(http_data, 4), small_limits()).await;
+        assert_eq!(result.err().unwrap(), ParseErrorKind::HeadersTooLarge);
+    }
+
     // ---------------------------------------
 
     #[test]
@@ -383,7 +1316,8 @@ mod test {
         let data = b"GET / HTTP/1.1\r\nSet-Person: lane\r\nSet-Person: prime\r\n\r\n";
         
         let _consumed = request.parse(data).unwrap();
-        assert_eq!("lane, prime", request.headers.get("set-person").unwrap());
+        assert_eq!(Some("lane"), request.headers.get_str("set-person"));
+        assert_eq!(vec!["lane", "prime"], request.headers.get_all("set-person").collect::<Vec<_>>());
     }
 
     #[test]
@@ -399,9 +1333,9 @@ mod test {
         let consumed = request.parse(b"GET / HTTP/1.1\r\nHost: localhost").unwrap();
         assert_eq!(consumed, 16); // "GET / HTTP/1.1\r\n" = 14 + 2
         assert_eq!(request.parser_state, ParserState::ParsingHeaders);
-        assert_eq!(request.request_line.method, "GET");
-        assert_eq!(request.request_line.request_target, "/");
-        assert_eq!(request.request_line.http_version, "1.1");
+        assert_eq!(request.request_line.method, Method::Get);
+        assert_eq!(request.request_line.request_target.raw(), "/");
+        assert_eq!(request.request_line.http_version, Version::Http11);
     }
 
     pub struct ChunkReader {
@@ -437,17 +1371,168 @@ mod test {
         }
     }
 
+    impl AsyncRead for ChunkReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            let n = std::io::Read::read(&mut *self, buf.initialize_unfilled())?;
+            buf.advance(n);
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_from_chunk_reader() {
+        let http_data = "GET /coffee HTTP/1.1\r\nHost: localhost:42069\r\nUser-Agent: curl/7.81.0\r\nAccept: */*\r\n\r\n";
+        let reader = ChunkReader::new(http_data, 3);
+
+        let request = request_from_reader(reader).await.unwrap();
+        assert_eq!(request.request_line.method, Method::Get);
+        assert_eq!(request.request_line.request_target.raw(), "/coffee");
+        assert_eq!("*/*", request.headers.get("accept").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests() {
+        let http_data = "GET /first HTTP/1.1\r\nHost: localhost\r\n\r\n\
+POST /second HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello\
+GET /third HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+        for bytes_per_read in [1, 3, 7, 1024] {
+            let mut reader = RequestReader::new(ChunkReader::new(http_data, bytes_per_read));
+
+            let first = reader.next_request().await.unwrap();
+            assert_eq!("/first", first.get_target());
+
+            let second = reader.next_request().await.unwrap();
+            assert_eq!("/second", second.get_target());
+            assert_eq!(b"hello", second.body.as_slice());
+
+            let third = reader.next_request().await.unwrap();
+            assert_eq!("/third", third.get_target());
+
+            assert_eq!(reader.next_request().await.err().unwrap(), ParseErrorKind::ConnectionClosed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_read_before_body() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut reader = RequestReader::new(server);
+
+        // the body isn't sent until the head has been answered
+        client.write_all(b"POST /upload HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n").await.unwrap();
+        let mut request = reader.next_request_head().await.unwrap();
+        assert_eq!("/upload", request.get_target());
+        assert!(request.expects_continue());
+        assert!(!request.is_complete());
+
+        client.write_all(b"hello").await.unwrap();
+        reader.read_body(&mut request).await.unwrap();
+        assert!(request.is_complete());
+        assert_eq!(b"hello", request.body.as_slice());
+    }
+
+    async fn collect_body(request: &mut Request) -> Result<Vec<Bytes>, std::io::Error> {
+        let mut chunks = Vec::new();
+        while let Some(chunk) = request.next_body_chunk().await? {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    #[tokio::test]
+    async fn test_streaming_request_body() {
+        let http_data = "POST /upload HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello world";
+        let mut request = streaming_request_from_reader(ChunkReader::new(http_data, 4)).await.unwrap();
+        assert_eq!("/upload", request.get_target());
+        assert!(!request.is_complete());
+
+        // decoded piece by piece as the reader hands it over
+        let chunks = collect_body(&mut request).await.unwrap();
+        assert!(chunks.len() > 1);
+        assert_eq!(b"hello world".to_vec(), chunks.concat());
+        assert!(request.is_complete());
+
+        let http_data = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 1\r\n\r\n";
+        let mut request = streaming_request_from_reader(ChunkReader::new(http_data, 3)).await.unwrap();
+        let body: Vec<Bytes> = request.body_stream().try_collect().await.unwrap();
+        assert_eq!(b"hello world".to_vec(), body.concat());
+        assert_eq!(Some("1"), request.trailers().get_str("x-sum"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_body_errors() {
+        let http_data = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n4\r\nmore\r\n0\r\n\r\n";
+        let mut request = streaming_request_from_reader_with_limits(ChunkReader::new(http_data, 1024), small_limits()).await.unwrap();
+        let error = collect_body(&mut request).await.unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert_eq!(Some(&ParseErrorKind::BodyTooLarge), error.get_ref().and_then(|e| e.downcast_ref::<ParseError>()).map(|e| e.kind()));
+
+        let http_data = "POST / HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello";
+        let mut request = streaming_request_from_reader(ChunkReader::new(http_data, 1024)).await.unwrap();
+        assert!(collect_body(&mut request).await.is_err());
+    }
+
+    #[test]
+    fn test_parts_round_trip() {
+        let mut request = Request::new();
+        request.parse(b"POST /users/7 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+        assert_eq!(&Method::Post, request.method());
+        assert_eq!(Some("localhost"), request.headers().get_str("host"));
+        assert_eq!(b"hello", request.body());
+
+        request.set_params(HashMap::from([("id".to_string(), "7".to_string())]));
+        request.extensions_mut().insert(42u32);
+        request.headers_mut().append("X-Seen", "1");
+
+        let (mut parts, body) = request.into_parts();
+        assert_eq!("/users/7", parts.target.raw());
+        assert_eq!(Version::Http11, parts.version);
+        parts.method = Method::Put;
+
+        let request = Request::from_parts(parts, body);
+        assert_eq!(&Method::Put, request.method());
+        assert_eq!(Some("7"), request.param("id"));
+        assert_eq!(Some(&42u32), request.extensions().get::<u32>());
+        assert_eq!(Some("1"), request.headers().get_str("x-seen"));
+        assert_eq!(b"hello", request.body());
+        assert!(request.is_complete());
+    }
+
+    #[test]
+    fn test_expectations() {
+        let mut request = Request::new();
+        request.parse(b"PUT / HTTP/1.1\r\nExpect: 100-Continue\r\n\r\n").unwrap();
+        assert!(request.expects_continue());
+        assert_eq!(None, request.unsupported_expectation());
+
+        let mut request = Request::new();
+        request.parse(b"PUT / HTTP/1.1\r\nExpect: 100-continue, x-fancy\r\n\r\n").unwrap();
+        assert_eq!(Some("x-fancy"), request.unsupported_expectation());
+
+        // HTTP/1.0 clients can't have meant it
+        let mut request = Request::new();
+        request.parse(b"PUT / HTTP/1.0\r\nExpect: 100-continue, x-fancy\r\n\r\n").unwrap();
+        assert!(!request.expects_continue());
+        assert_eq!(None, request.unsupported_expectation());
+    }
+
     #[test]
     fn test_chunk_reader_basics() {
         let http_data = "GET /coffee HTTP/1.1\r\nHost: localhost:42069\r\nUser-Agent: curl/7.81.0\r\nAccept: */*\r\n\r\n";
         let mut reader = ChunkReader::new(http_data, 3);
         let mut buf = [0u8; 10];
 
-        let n = reader.read(&mut buf).unwrap();
+        let n = Read::read(&mut reader, &mut buf).unwrap();
         assert_eq!(n, 3);
         assert_eq!(&buf[..n], b"GET");
 
-        let n = reader.read(&mut buf).unwrap();
+        let n = Read::read(&mut reader, &mut buf).unwrap();
         assert_eq!(n, 3);
         assert_eq!(&buf[..n], b" /c");
 
@@ -1,23 +1,150 @@
-use crate::{http::headers::Headers};
-use tokio::net::TcpStream;
-use tokio::io::AsyncWriteExt;
+use crate::http::body::Body;
+use crate::http::headers::{has_token, HeaderName, HeaderValue, Headers};
+use crate::http::typed_headers::{is_token, ContentLength, ContentType, TransferEncoding, TypedHeader};
+use crate::http::version::Version;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub enum StatusCode {
-    StatusOk,
-    StatusBadRequest,
-    StatusInternalServerError,
-    StatusNotFound,
+macro_rules! status_codes {
+    ($( ($variant:ident, $code:literal, $reason:literal), )+) => {
+        /// Every status code in the IANA HTTP Status Code Registry (unused
+        /// entries such as 306 and 418 left out).
+        #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+        pub enum StatusCode {
+            $( $variant, )+
+        }
+
+        impl StatusCode {
+            pub fn as_u16(&self) -> u16 {
+                match self {
+                    $( StatusCode::$variant => $code, )+
+                }
+            }
+
+            /// Looks up a registered code; unregistered numbers give `None`.
+            pub fn from_u16(code: u16) -> Option<StatusCode> {
+                match code {
+                    $( $code => Some(StatusCode::$variant), )+
+                    _ => None,
+                }
+            }
+
+            /// The canonical reason phrase from RFC 9110 / the IANA registry.
+            pub fn reason_phrase(&self) -> &'static str {
+                match self {
+                    $( StatusCode::$variant => $reason, )+
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    (StatusContinue, 100, "Continue"),
+    (StatusSwitchingProtocols, 101, "Switching Protocols"),
+    (StatusProcessing, 102, "Processing"),
+    (StatusEarlyHints, 103, "Early Hints"),
+
+    (StatusOk, 200, "OK"),
+    (StatusCreated, 201, "Created"),
+    (StatusAccepted, 202, "Accepted"),
+    (StatusNonAuthoritativeInformation, 203, "Non-Authoritative Information"),
+    (StatusNoContent, 204, "No Content"),
+    (StatusResetContent, 205, "Reset Content"),
+    (StatusPartialContent, 206, "Partial Content"),
+    (StatusMultiStatus, 207, "Multi-Status"),
+    (StatusAlreadyReported, 208, "Already Reported"),
+    (StatusImUsed, 226, "IM Used"),
+
+    (StatusMultipleChoices, 300, "Multiple Choices"),
+    (StatusMovedPermanently, 301, "Moved Permanently"),
+    (StatusFound, 302, "Found"),
+    (StatusSeeOther, 303, "See Other"),
+    (StatusNotModified, 304, "Not Modified"),
+    (StatusUseProxy, 305, "Use Proxy"),
+    (StatusTemporaryRedirect, 307, "Temporary Redirect"),
+    (StatusPermanentRedirect, 308, "Permanent Redirect"),
+
+    (StatusBadRequest, 400, "Bad Request"),
+    (StatusUnauthorized, 401, "Unauthorized"),
+    (StatusPaymentRequired, 402, "Payment Required"),
+    (StatusForbidden, 403, "Forbidden"),
+    (StatusNotFound, 404, "Not Found"),
+    (StatusMethodNotAllowed, 405, "Method Not Allowed"),
+    (StatusNotAcceptable, 406, "Not Acceptable"),
+    (StatusProxyAuthenticationRequired, 407, "Proxy Authentication Required"),
+    (StatusRequestTimeout, 408, "Request Timeout"),
+    (StatusConflict, 409, "Conflict"),
+    (StatusGone, 410, "Gone"),
+    (StatusLengthRequired, 411, "Length Required"),
+    (StatusPreconditionFailed, 412, "Precondition Failed"),
+    (StatusContentTooLarge, 413, "Content Too Large"),
+    (StatusUriTooLong, 414, "URI Too Long"),
+    (StatusUnsupportedMediaType, 415, "Unsupported Media Type"),
+    (StatusRangeNotSatisfiable, 416, "Range Not Satisfiable"),
+    (StatusExpectationFailed, 417, "Expectation Failed"),
+    (StatusMisdirectedRequest, 421, "Misdirected Request"),
+    (StatusUnprocessableContent, 422, "Unprocessable Content"),
+    (StatusLocked, 423, "Locked"),
+    (StatusFailedDependency, 424, "Failed Dependency"),
+    (StatusTooEarly, 425, "Too Early"),
+    (StatusUpgradeRequired, 426, "Upgrade Required"),
+    (StatusPreconditionRequired, 428, "Precondition Required"),
+    (StatusTooManyRequests, 429, "Too Many Requests"),
+    (StatusRequestHeaderFieldsTooLarge, 431, "Request Header Fields Too Large"),
+    (StatusUnavailableForLegalReasons, 451, "Unavailable For Legal Reasons"),
+
+    (StatusInternalServerError, 500, "Internal Server Error"),
+    (StatusNotImplemented, 501, "Not Implemented"),
+    (StatusBadGateway, 502, "Bad Gateway"),
+    (StatusServiceUnavailable, 503, "Service Unavailable"),
+    (StatusGatewayTimeout, 504, "Gateway Timeout"),
+    (StatusHttpVersionNotSupported, 505, "HTTP Version Not Supported"),
+    (StatusVariantAlsoNegotiates, 506, "Variant Also Negotiates"),
+    (StatusInsufficientStorage, 507, "Insufficient Storage"),
+    (StatusLoopDetected, 508, "Loop Detected"),
+    (StatusNotExtended, 510, "Not Extended"),
+    (StatusNetworkAuthenticationRequired, 511, "Network Authentication Required"),
+}
+
+impl StatusCode {
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.as_u16())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.as_u16())
+    }
+
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.as_u16())
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.as_u16())
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.as_u16())
+    }
+
+    /// Whether a response with this status may carry content; 1xx, 204 and 304
+    /// never do (RFC 9112 section 6.3).
+    pub fn allows_body(&self) -> bool {
+        !self.is_informational() && !matches!(self, StatusCode::StatusNoContent | StatusCode::StatusNotModified)
+    }
+}
+
+impl TryFrom<u16> for StatusCode {
+    type Error = u16;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        StatusCode::from_u16(code).ok_or(code)
+    }
 }
 
 impl std::fmt::Display for StatusCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            StatusCode::StatusOk => write!(f, "HTTP/1.1 200 OK"),
-            StatusCode::StatusBadRequest  => write!(f, "HTTP/1.1 400 Bad Request"),
-            StatusCode::StatusInternalServerError => write!(f, "HTTP/1.1 500 Internal Server Error"),
-            StatusCode::StatusNotFound => write!(f, "HTTP/1.1 404 File Not Found"),
-        }
+        write!(f, "{} {}", self.as_u16(), self.reason_phrase())
     }
 }
 
@@ -27,22 +154,154 @@ enum WriterState {
     StatusWritten,
     HeadersWritten,
     BodyWritten,
+    Finished,
 }
 
-pub struct Writer {
-    stream: TcpStream,
+/// Type-erased transport that handlers write through, so one `Handler` can
+/// serve TCP, TLS, Unix sockets or in-memory streams alike.
+pub type BoxedWrite = Box<dyn AsyncWrite + Send + Unpin>;
+
+pub struct Writer<W = BoxedWrite> {
+    stream: W,
     state: WriterState,
+    status: Option<StatusCode>,
+    version: Version,
+    keep_alive: bool,
+    chunked: bool,
+    content_length: Option<usize>,
+    /// Body bytes sent so far under `content_length` framing.
+    body_written: usize,
+    chunk_buf: Vec<u8>,
+    chunk_buffer_size: usize,
+    declared_trailers: Vec<HeaderName>,
+    trailers_accepted: bool,
+    force_trailers: bool,
+    head: bool,
 }
 
-impl Writer {
-    pub fn new(stream: TcpStream) -> Self {
+impl<W> Writer<W>
+    where W: AsyncWrite + Unpin
+{
+    pub fn new(stream: W) -> Self {
         Writer {
             stream,
             state: WriterState::New,
+            status: None,
+            version: Version::Http11,
+            keep_alive: false,
+            chunked: false,
+            content_length: None,
+            body_written: 0,
+            chunk_buf: Vec::new(),
+            chunk_buffer_size: 0,
+            declared_trailers: Vec::new(),
+            trailers_accepted: false,
+            force_trailers: false,
+            head: false,
+        }
+    }
+
+    /// Prepares the writer for the next response on a persistent connection,
+    /// answering in the same protocol version the request used.
+    pub(crate) fn reset(&mut self, version: Version, keep_alive: bool) {
+        self.state = WriterState::New;
+        self.status = None;
+        self.version = version;
+        self.keep_alive = keep_alive;
+        self.chunked = false;
+        self.content_length = None;
+        self.body_written = 0;
+        self.chunk_buf.clear();
+        self.declared_trailers.clear();
+        self.trailers_accepted = false;
+        self.force_trailers = false;
+        self.head = false;
+    }
+
+    /// Marks the current response as the answer to a HEAD request: status and
+    /// headers are sent as for GET, body writes are accepted and dropped.
+    pub(crate) fn set_head_request(&mut self, head: bool) {
+        self.head = head;
+    }
+
+    /// Records whether the client sent `TE: trailers` for the current request.
+    pub(crate) fn set_trailers_accepted(&mut self, accepted: bool) {
+        self.trailers_accepted = accepted;
+    }
+
+    /// Whether `write_trailers` will send trailers for this response.
+    pub fn trailers_accepted(&self) -> bool {
+        self.trailers_accepted || self.force_trailers
+    }
+
+    /// Sends trailers for the current response even though the client didn't
+    /// ask for them with `TE: trailers`. They are still checked against the
+    /// `Trailer` header.
+    pub fn force_trailers(&mut self) {
+        self.force_trailers = true;
+    }
+
+    /// Coalesces chunked body writes until `size` bytes are pending, so many
+    /// small writes go out as a few large chunks. Zero, the default, sends
+    /// every write as its own chunk. `flush` pushes out a partial buffer.
+    pub fn set_chunk_buffer_size(&mut self, size: usize) {
+        self.chunk_buffer_size = size;
+    }
+
+    /// Whether the connection can carry another request after this response.
+    pub fn keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+
+    /// Whether chunk framing actually goes on the wire. HTTP/1.0 clients don't
+    /// understand it, so a chunked response to them is sent close-delimited.
+    fn encode_chunks(&self) -> bool {
+        self.chunked && self.version.supports_chunked()
+    }
+
+    /// Whether body bytes actually go on the wire; not for HEAD requests or
+    /// statuses that can't have content.
+    fn allows_body(&self) -> bool {
+        !self.head && self.status.is_none_or(|s| s.allows_body())
+    }
+
+    /// Whether anything has been written for the current response yet.
+    pub(crate) fn has_started(&self) -> bool {
+        self.state != WriterState::New
+    }
+
+    /// Whether a fully framed response has been written, so the next response
+    /// on the connection won't be mistaken for part of this one's body. A
+    /// body shorter than its `Content-Length` never is.
+    pub(crate) fn is_complete(&self) -> bool {
+        let length_met = self.content_length.is_none_or(|n| n == self.body_written);
+        match self.state {
+            WriterState::Finished => !self.allows_body() || self.chunked || length_met,
+            WriterState::BodyWritten => !self.allows_body() || (!self.chunked && length_met),
+            WriterState::HeadersWritten => !self.allows_body() || (!self.chunked && self.content_length == Some(0)),
+            _ => false,
         }
     }
 
     pub async fn write_status_line(&mut self, status_code: StatusCode) -> Result<(), std::io::Error> {
+        self.write_status_line_with_reason(status_code, status_code.reason_phrase()).await
+    }
+
+    /// Like `write_status_line` but with a custom reason phrase, e.g.
+    /// `404 Video Not Found`. Clients must not rely on the phrase, only the code.
+    pub async fn write_status_line_with_reason(&mut self, status_code: StatusCode, reason: &str) -> Result<(), std::io::Error> {
         if self.state != WriterState::New {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -50,11 +309,53 @@ impl Writer {
             ));
         }
 
-        self.stream.write_all(&format!("{}\r\n", status_code).as_bytes()).await?;
+        // reason-phrase = *( HTAB / SP / VCHAR / obs-text )
+        if reason.bytes().any(|b| b != b'\t' && b.is_ascii_control()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "reason phrase contains control characters"
+            ));
+        }
+
+        let status_line = format!("{} {} {}\r\n", self.version, status_code.as_u16(), reason);
+        self.stream.write_all(status_line.as_bytes()).await?;
         self.state = WriterState::StatusWritten;
+        self.status = Some(status_code);
         Ok(())
     }
 
+    /// Sends an interim 1xx response such as `103 Early Hints` ahead of the
+    /// final one, which still has to be written afterwards. HTTP/1.0 clients
+    /// don't understand interim responses, so nothing is sent to them.
+    pub async fn write_informational(&mut self, status_code: StatusCode, headers: &Headers) -> Result<(), std::io::Error> {
+        if self.state != WriterState::New {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "informational responses must come before the final status line"
+            ));
+        }
+        // 101 hands the connection to another protocol, which we don't do
+        if !status_code.is_informational() || status_code == StatusCode::StatusSwitchingProtocols {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not an informational status", status_code.as_u16())
+            ));
+        }
+        if self.version < Version::Http11 {
+            return Ok(());
+        }
+
+        let mut data = format!("{} {} {}\r\n", self.version, status_code.as_u16(), status_code.reason_phrase()).into_bytes();
+        data.extend_from_slice(&headers.to_wire()?);
+        data.extend_from_slice(b"\r\n");
+        self.stream.write_all(&data).await
+    }
+
+    /// Tells a client that sent `Expect: 100-continue` to go ahead with the body.
+    pub async fn write_continue(&mut self) -> Result<(), std::io::Error> {
+        self.write_informational(StatusCode::StatusContinue, &Headers::new()).await
+    }
+
     pub async fn write_headers(&mut self, headers: &Headers) -> Result<(), std::io::Error> {
         if self.state != WriterState::StatusWritten {
             return Err(std::io::Error::new(
@@ -63,7 +364,47 @@ impl Writer {
             ));
         }
 
-        self.stream.write_all(&format!("{}\r\n\r\n", headers).as_bytes()).await?;
+        let mut has_connection = false;
+        let mut sent = Headers::new();
+        for (key, value) in headers.iter() {
+            let text = value.to_str().unwrap_or_default();
+            if *key == HeaderName::CONNECTION {
+                has_connection = true;
+                if has_token(text, "close") {
+                    self.keep_alive = false;
+                }
+            } else if *key == HeaderName::TRANSFER_ENCODING {
+                self.chunked = has_token(text, "chunked");
+                if !self.version.supports_chunked() {
+                    continue;
+                }
+            } else if *key == HeaderName::TRAILER {
+                if !self.version.supports_chunked() {
+                    continue;
+                }
+                let names = text.split(',').map(|n| n.trim()).filter(|n| !n.is_empty());
+                self.declared_trailers.extend(names.map(|n| HeaderName::from(n.to_string())));
+            } else if *key == HeaderName::CONTENT_LENGTH {
+                self.content_length = text.parse().ok();
+            }
+            sent.append(key, value.clone());
+        }
+
+        // without a length or chunked framing the body can only end when the
+        // connection does
+        if self.allows_body() && !self.encode_chunks() && self.content_length.is_none() {
+            self.keep_alive = false;
+        }
+        if !self.keep_alive {
+            // whatever the handler asked for, the connection is going away
+            sent.set(HeaderName::CONNECTION, "close");
+        } else if !has_connection && !self.version.keep_alive_by_default() {
+            sent.append(HeaderName::CONNECTION, "keep-alive");
+        }
+        let mut header_data = sent.to_wire()?;
+        header_data.extend_from_slice(b"\r\n");
+
+        self.stream.write_all(&header_data).await?;
         self.state = WriterState::HeadersWritten;
         Ok(())
     }
@@ -76,12 +417,20 @@ impl Writer {
             ));
         }
 
+        if !self.allows_body() {
+            self.state = WriterState::BodyWritten;
+            return Ok(body.len());
+        }
+
         self.stream.write_all(body).await?;
         self.stream.flush().await?;
+        self.body_written += body.len();
         self.state = WriterState::BodyWritten;
         Ok(body.len())
     }
 
+    /// Queues `body` as chunked content, returning how many bytes were taken.
+    /// An empty `body` is skipped, since a zero-size chunk would end the body.
     pub async fn write_chunked_body(&mut self, body: &[u8]) -> Result<usize, std::io::Error> {
         if self.state != WriterState::HeadersWritten {
             return Err(std::io::Error::new(
@@ -90,22 +439,78 @@ impl Writer {
             ));
         }
 
-        let chunk_size = body.len();
+        if !self.allows_body() {
+            return Ok(body.len());
+        }
+        if !self.encode_chunks() {
+            self.stream.write_all(body).await?;
+            return Ok(body.len());
+        }
+
+        self.chunk_buf.extend_from_slice(body);
+        if self.chunk_buf.len() >= self.chunk_buffer_size {
+            self.flush_chunk().await?;
+        }
+        Ok(body.len())
+    }
+
+    /// Sends `body` as one chunk carrying the given chunk extensions, e.g.
+    /// `[("sig", Some("abc"))]` for `;sig=abc`. Anything buffered goes first.
+    pub async fn write_chunk_with_extensions(&mut self, body: &[u8], extensions: &[(&str, Option<&str>)]) -> Result<usize, std::io::Error> {
+        if self.state != WriterState::HeadersWritten {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "body must be written after headers"
+            ));
+        }
+
+        let ext = encode_chunk_extensions(extensions)?;
 
-        let mut n_total = 0;
-        let n = self.stream.write(&format!("{:X}\r\n", chunk_size).as_bytes()).await?;
-        n_total += n;
+        if !self.allows_body() {
+            return Ok(body.len());
+        }
+        if !self.encode_chunks() {
+            self.stream.write_all(body).await?;
+            return Ok(body.len());
+        }
+        if body.is_empty() {
+            return Ok(0);
+        }
 
-        let n = self.stream.write(body).await?;
-        n_total += n;
+        self.flush_chunk().await?;
+        self.write_chunk(body, &ext).await?;
+        Ok(body.len())
+    }
 
-        let n = self.stream.write("\r\n".as_bytes()).await?;
-        n_total += n;
+    /// Writes size line, data and CRLF in a single `write_all`, so a chunk is
+    /// never left half-framed on the wire.
+    async fn write_chunk(&mut self, data: &[u8], ext: &str) -> Result<(), std::io::Error> {
+        let mut chunk = format!("{:X}{}\r\n", data.len(), ext).into_bytes();
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(b"\r\n");
+        self.stream.write_all(&chunk).await
+    }
 
+    async fn flush_chunk(&mut self) -> Result<(), std::io::Error> {
+        if self.chunk_buf.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::take(&mut self.chunk_buf);
+        let result = self.write_chunk(&data, "").await;
+        // hand the allocation back for the next chunk
+        self.chunk_buf = data;
+        self.chunk_buf.clear();
+        result
+    }
 
-        Ok(n_total)
+    /// Sends any buffered chunk data and flushes the underlying stream.
+    pub async fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.flush_chunk().await?;
+        self.stream.flush().await
     }
 
+    /// Ends the chunked body with the last-chunk. Trailers may follow, then
+    /// `finish` writes the closing CRLF.
     pub async fn write_chunked_body_done(&mut self) -> Result<usize, std::io::Error> {
         if self.state != WriterState::HeadersWritten {
             return Err(std::io::Error::new(
@@ -113,9 +518,14 @@ impl Writer {
                 "body must be written after headers"
             ));
         }
-        let n = self.stream.write("0\r\n".as_bytes()).await?;
+        if !self.encode_chunks() || !self.allows_body() {
+            self.state = WriterState::BodyWritten;
+            return Ok(0);
+        }
+        self.flush_chunk().await?;
+        self.stream.write_all(b"0\r\n").await?;
         self.state = WriterState::BodyWritten;
-        Ok(n)
+        Ok(3)
     }
 
     pub async fn write_trailers(&mut self, headers: &Headers) -> Result<usize, std::io::Error> {
@@ -126,24 +536,183 @@ impl Writer {
             ));
         }
 
-        let trailer_data = format!("{}\r\n", headers);
-        self.stream.write(trailer_data.as_bytes()).await?;
+        // trailers only exist inside chunk framing
+        if !self.encode_chunks() || !self.allows_body() {
+            return Ok(0);
+        }
+
+        if !self.trailers_accepted() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "client did not send TE: trailers"
+            ));
+        }
+        for (name, _) in headers.iter() {
+            if is_forbidden_trailer(name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{} is not allowed as a trailer field", name)
+                ));
+            }
+            if !self.declared_trailers.contains(name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("trailer {} was not declared in the Trailer header", name)
+                ));
+            }
+        }
+
+        let trailer_data = headers.to_wire()?;
+        self.stream.write_all(&trailer_data).await?;
         self.stream.flush().await?;
 
         Ok(trailer_data.len())
     }
 
+    /// Writes a complete response in one go, driving its `Body` to the end.
+    ///
+    /// Framing comes from the body, replacing whatever `Content-Length` or
+    /// `Transfer-Encoding` the response carried: a body of known size gets a
+    /// `Content-Length`, anything else is sent chunked. Statuses that can't
+    /// have content are sent without a body, and 1xx and 204 without a
+    /// `Content-Length` either (RFC 9110 section 8.6).
+    pub async fn send(&mut self, response: Response) -> Result<(), std::io::Error> {
+        let Response { status_line, mut headers, mut body } = response;
+
+        self.write_status_line(status_line).await?;
+        headers.remove_entry(HeaderName::TRANSFER_ENCODING);
+        if status_line.is_informational() || status_line == StatusCode::StatusNoContent {
+            headers.remove_entry(HeaderName::CONTENT_LENGTH);
+        }
+        if !status_line.allows_body() {
+            self.write_headers(&headers).await?;
+            self.stream.flush().await?;
+            return Ok(());
+        }
+
+        match body.len() {
+            Some(len) => {
+                headers.typed_insert(ContentLength(len));
+                self.write_headers(&headers).await?;
+                if self.head {
+                    return self.write_body(&[]).await.map(|_| ());
+                }
+
+                let mut written = 0;
+                while let Some(chunk) = body.next_chunk().await? {
+                    written += chunk.len();
+                    if written > len {
+                        break;
+                    }
+                    self.stream.write_all(&chunk).await?;
+                }
+                if written != len {
+                    // the framing is already on the wire, so the connection can't be reused
+                    self.keep_alive = false;
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "body length did not match its content-length"
+                    ));
+                }
+                self.stream.flush().await?;
+                self.body_written = written;
+                self.state = WriterState::BodyWritten;
+            },
+            None => {
+                headers.remove_entry(HeaderName::CONTENT_LENGTH);
+                headers.typed_insert(TransferEncoding::chunked());
+                self.write_headers(&headers).await?;
+                if self.head {
+                    return self.finish().await.map(|_| ());
+                }
+
+                while let Some(chunk) = body.next_chunk().await? {
+                    self.write_chunked_body(&chunk).await?;
+                }
+                self.write_chunked_body_done().await?;
+                self.finish().await?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Flushes and half-closes the underlying stream.
+    pub async fn shutdown(&mut self) -> Result<(), std::io::Error> {
+        self.stream.flush().await?;
+        self.stream.shutdown().await
+    }
+
+    /// Completes the response. For a chunked body this writes whatever part
+    /// of the last-chunk/trailer/CRLF sequence is still missing, so the body
+    /// is always properly terminated.
     pub async fn finish(&mut self) -> Result<usize, std::io::Error> {
-        let n = self.stream.write(b"\r\n").await?;
+        let mut n = 0;
+        if self.chunked && self.state == WriterState::HeadersWritten {
+            n += self.write_chunked_body_done().await?;
+        }
+        if self.encode_chunks() && self.allows_body() && self.state == WriterState::BodyWritten {
+            self.stream.write_all(b"\r\n").await?;
+            n += 2;
+        }
         self.stream.flush().await?;
+        self.state = WriterState::Finished;
         Ok(n)
     }
+
+    /// Terminates a chunked body the handler left open. Called by the server
+    /// once a handler returns successfully.
+    pub(crate) async fn complete(&mut self) -> Result<(), std::io::Error> {
+        if self.chunked && matches!(self.state, WriterState::HeadersWritten | WriterState::BodyWritten) {
+            self.finish().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialises chunk extensions as `;name=value` pairs, validated before any of
+/// them reach the wire so they can't break the chunk framing.
+///
+/// chunk-ext = *( BWS ";" BWS token [ BWS "=" BWS ( token / quoted-string ) ] )
+fn encode_chunk_extensions(extensions: &[(&str, Option<&str>)]) -> Result<String, std::io::Error> {
+    let mut ext = String::new();
+    for (name, value) in extensions {
+        if !is_token(name) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "chunk extension name is not a token"));
+        }
+        ext.push(';');
+        ext.push_str(name);
+        match value {
+            Some(v) if is_token(v) => ext.push_str(&format!("={}", v)),
+            // qdtext and quoted-pair cover HTAB, SP and VCHAR; CR, LF and other controls can't be quoted
+            Some(v) if v.bytes().all(|b| b == b'\t' || (b' '..=b'~').contains(&b)) => {
+                ext.push_str(&format!("=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")));
+            },
+            Some(_) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "chunk extension value contains control characters")),
+            None => {},
+        }
+    }
+    Ok(ext)
+}
+
+/// Fields that must not be sent as trailers (RFC 9110 section 6.5.1): those
+/// that frame or route the message, modify the request, authenticate, control
+/// caching or describe the content, plus `Trailer` itself.
+const FORBIDDEN_TRAILERS: &[&str] = &[
+    "age", "authorization", "cache-control", "connection", "content-encoding",
+    "content-length", "content-range", "content-type", "date", "expect", "expires",
+    "host", "keep-alive", "location", "max-forwards", "pragma", "proxy-authenticate",
+    "proxy-authorization", "range", "retry-after", "set-cookie", "te", "trailer",
+    "transfer-encoding", "upgrade", "vary", "www-authenticate",
+];
+
+fn is_forbidden_trailer(name: &HeaderName) -> bool {
+    FORBIDDEN_TRAILERS.iter().any(|f| name == f) || name.as_str().to_ascii_lowercase().starts_with("if-")
 }
 
 pub struct Response {
     status_line: StatusCode,
     pub headers: Headers,
-    pub body: Vec<u8>,
+    pub body: Body,
 }
 
 impl Default for Response {
@@ -151,43 +720,595 @@ impl Default for Response {
         Response {
             status_line: StatusCode::StatusOk,
             headers: Self::get_default_headers(0),
-            body: Vec::new(),
+            body: Body::empty(),
         }
     }
 }
 
+/// For debugging only. A `Response` doesn't know which version it will be
+/// sent in, so this always shows HTTP/1.1; the `Writer` picks the real one.
 impl std::fmt::Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\r\n{}\r\n\r\n{}\r\n",
+        write!(f, "{} {}\r\n{}\r\n\r\n{}\r\n",
+            Version::Http11,
             self.status_line,
             self.headers,
-            String::from_utf8_lossy(&self.body)
+            String::from_utf8_lossy(self.body.as_bytes().unwrap_or(b"<streaming body>"))
         )
     }
 }
 
 impl Response {
 
-    pub fn new(status_code: StatusCode, body: Vec<u8>) -> Self {
-        Response {
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder::new()
+    }
+
+    pub fn new(status_code: StatusCode, body: impl Into<Body>) -> Self {
+        let mut response = Response {
             status_line: status_code,
-            headers: Self::get_default_headers(body.len()),
-            body,
-        }
+            headers: Self::get_default_headers(0),
+            body: Body::empty(),
+        };
+        response.set_body(body);
+        response
     }
 
     pub fn get_default_headers(content_len: usize) -> Headers {
         let mut result = Headers::new();
-        result.insert("Content-Length".to_string(), content_len.to_string());
-        result.insert("Connection".to_string(), "close".to_string());
-        result.insert("Content-Type".to_string(), "text/plain".to_string());
+        result.typed_insert(ContentLength(content_len));
+        result.typed_insert(ContentType::text_plain());
 
         result
     }
 
-    pub fn set_body(&mut self, body: Vec<u8>) {
-        let content_length = body.len();
-        self.body = body;
-        self.headers.insert("content-length".to_string(), content_length.to_string());
+    /// Replaces the body, keeping `Content-Length` in step with it.
+    pub fn set_body(&mut self, body: impl Into<Body>) {
+        self.body = body.into();
+        match self.body.len() {
+            Some(len) => self.headers.typed_insert(ContentLength(len)),
+            None => self.headers.remove_entry(HeaderName::CONTENT_LENGTH),
+        }
     }
-}
\ No newline at end of file
+
+    pub fn status(&self) -> StatusCode {
+        self.status_line
+    }
+}
+
+/// Builds a `Response` for `Writer::send`, e.g.
+/// `Response::builder().status(StatusCode::StatusNotFound).body("gone")`.
+///
+/// Unlike `Response::new` no headers are added up front; `Writer::send` fills
+/// in the framing.
+pub struct ResponseBuilder {
+    status: StatusCode,
+    headers: Headers,
+}
+
+impl Default for ResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseBuilder {
+    pub fn new() -> Self {
+        ResponseBuilder {
+            status: StatusCode::StatusOk,
+            headers: Headers::new(),
+        }
+    }
+
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Adds a header field, keeping any earlier values with the same name.
+    pub fn header(mut self, name: impl Into<HeaderName>, value: impl Into<HeaderValue>) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn typed_header<T: TypedHeader>(mut self, header: T) -> Self {
+        self.headers.typed_insert(header);
+        self
+    }
+
+    pub fn body(self, body: impl Into<Body>) -> Response {
+        Response {
+            status_line: self.status,
+            headers: self.headers,
+            body: body.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn body_of(written: &[u8]) -> &[u8] {
+        let start = written.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        &written[start..]
+    }
+
+    #[tokio::test]
+    async fn test_writer_into_vec() {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&Response::get_default_headers(5)).await.unwrap();
+        writer.write_body(b"hello").await.unwrap();
+
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(written.contains("Content-Length: 5\r\n"));
+        assert!(written.contains("Connection: close\r\n"));
+        assert!(written.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn test_closing_writer_overrides_handler_connection() {
+        for version in [Version::Http11, Version::Http10] {
+            let mut headers = Response::get_default_headers(0);
+            headers.insert(HeaderName::CONNECTION, "keep-alive");
+
+            let mut writer = Writer::new(Vec::new());
+            writer.reset(version, false);
+            writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+            writer.write_headers(&headers).await.unwrap();
+
+            let written = String::from_utf8(writer.into_inner()).unwrap();
+            assert!(written.contains("Connection: close\r\n"), "{}", written);
+            assert!(!written.contains("keep-alive"), "{}", written);
+        }
+
+        // a handler's own tokens stand while the connection stays open
+        let mut headers = Response::get_default_headers(0);
+        headers.insert(HeaderName::CONNECTION, "keep-alive");
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http10, true);
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        assert_eq!(1, String::from_utf8(writer.into_inner()).unwrap().matches("Connection: keep-alive\r\n").count());
+    }
+
+    #[tokio::test]
+    async fn test_writer_state_order_enforced() {
+        let mut writer = Writer::new(Vec::new());
+        assert!(writer.write_headers(&Headers::new()).await.is_err());
+        assert!(writer.write_body(b"early").await.is_err());
+
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        assert!(writer.write_status_line(StatusCode::StatusOk).await.is_err());
+        assert!(writer.get_ref().starts_with(b"HTTP/1.1 200 OK"));
+    }
+
+    #[tokio::test]
+    async fn test_writer_custom_reason_phrase() {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_status_line_with_reason(StatusCode::StatusNotFound, "Video Not Found").await.unwrap();
+        assert_eq!(b"HTTP/1.1 404 Video Not Found\r\n", writer.get_ref().as_slice());
+
+        let mut writer = Writer::new(Vec::new());
+        assert!(writer.write_status_line_with_reason(StatusCode::StatusOk, "OK\r\nX-Injected: 1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_writer_rejects_header_injection() {
+        let mut writer = Writer::new(Vec::new());
+        let mut headers = Headers::new();
+        headers.insert("Location", "/next\r\nSet-Cookie: stolen=1");
+
+        writer.write_status_line(StatusCode::StatusFound).await.unwrap();
+        assert!(writer.write_headers(&headers).await.is_err());
+        assert!(!writer.get_ref().windows(10).any(|w| w == b"Set-Cookie"));
+
+        let mut writer = Writer::new(Vec::new());
+        let mut headers = Headers::new();
+        headers.insert("X-A: 1\r\nSet-Cookie".to_string(), "evil");
+
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        assert!(writer.write_headers(&headers).await.is_err());
+        assert!(!writer.get_ref().windows(10).any(|w| w == b"Set-Cookie"));
+    }
+
+    #[tokio::test]
+    async fn test_writer_chunked_body() {
+        let mut writer = Writer::new(Vec::new());
+        let mut headers = Headers::new();
+        headers.insert("Transfer-Encoding".to_string(), "chunked".to_string());
+
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        writer.write_chunked_body(b"hello world!").await.unwrap();
+        writer.write_chunked_body_done().await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(b"C\r\nhello world!\r\n0\r\n\r\n", body_of(&writer.into_inner()));
+    }
+
+    /// Accepts at most one byte per write, like a congested socket.
+    struct Trickle(Vec<u8>);
+
+    impl AsyncWrite for Trickle {
+        fn poll_write(mut self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>, buf: &[u8]) -> std::task::Poll<std::io::Result<usize>> {
+            let n = buf.len().min(1);
+            self.0.extend_from_slice(&buf[..n]);
+            std::task::Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn chunked_writer<W: AsyncWrite + Unpin>(stream: W) -> Writer<W> {
+        let mut writer = Writer::new(stream);
+        let mut headers = Headers::new();
+        headers.typed_insert(TransferEncoding::chunked());
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        writer
+    }
+
+    #[tokio::test]
+    async fn test_chunks_survive_partial_writes() {
+        let mut writer = chunked_writer(Trickle(Vec::new())).await;
+        writer.write_chunked_body(b"hello").await.unwrap();
+        writer.write_chunked_body(b"").await.unwrap();
+        writer.write_chunked_body(b" world").await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n", body_of(&writer.into_inner().0));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_coalescing() {
+        let mut writer = chunked_writer(Vec::new()).await;
+        writer.set_chunk_buffer_size(8);
+        writer.write_chunked_body(b"abc").await.unwrap();
+        writer.write_chunked_body(b"def").await.unwrap();
+        assert_eq!(b"", body_of(writer.get_ref()));
+        writer.write_chunked_body(b"ghi").await.unwrap();
+        writer.write_chunked_body(b"jk").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_chunked_body(b"l").await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(b"9\r\nabcdefghi\r\n2\r\njk\r\n1\r\nl\r\n0\r\n\r\n", body_of(&writer.into_inner()));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_extensions() {
+        let mut writer = chunked_writer(Vec::new()).await;
+        writer.write_chunk_with_extensions(b"data", &[("sig", Some("abc")), ("note", Some("two words")), ("last", None)]).await.unwrap();
+        assert!(writer.write_chunk_with_extensions(b"x", &[("bad name", None)]).await.is_err());
+        assert!(writer.write_chunk_with_extensions(b"x", &[("n", Some("a\r\nb"))]).await.is_err());
+        writer.finish().await.unwrap();
+
+        assert_eq!(b"4;sig=abc;note=\"two words\";last\r\ndata\r\n0\r\n\r\n", body_of(&writer.into_inner()));
+    }
+
+    #[test]
+    fn test_chunk_extensions_validated() {
+        for name in ["", "a\r\n0\r\n\r\nX", "a;b", "a=b", "a\"b", "caf\u{e9}"] {
+            assert!(encode_chunk_extensions(&[(name, None)]).is_err(), "{:?}", name);
+        }
+        for value in ["a\r\nb", "a\nb", "a\rb", "a\0b", "a\x7fb", "caf\u{e9}"] {
+            assert!(encode_chunk_extensions(&[("n", Some(value))]).is_err(), "{:?}", value);
+        }
+        // one bad extension rejects the lot
+        assert!(encode_chunk_extensions(&[("ok", None), ("bad\n", None)]).is_err());
+
+        assert_eq!(";n=\"a\\\"b\\\\c;d\"", encode_chunk_extensions(&[("n", Some("a\"b\\c;d"))]).unwrap());
+        assert_eq!(";n=\"\"", encode_chunk_extensions(&[("n", Some(""))]).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_short_body_is_incomplete() {
+        let mut headers = Headers::new();
+        headers.typed_insert(ContentLength(100));
+
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        writer.write_body(b"only ten!!").await.unwrap();
+        assert!(!writer.is_complete());
+        writer.finish().await.unwrap();
+        assert!(!writer.is_complete());
+
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        writer.write_body(&[b'x'; 100]).await.unwrap();
+        assert!(writer.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_finish_terminates_open_chunked_body() {
+        let mut writer = chunked_writer(Vec::new()).await;
+        writer.set_chunk_buffer_size(1024);
+        writer.write_chunked_body(b"pending").await.unwrap();
+        writer.complete().await.unwrap();
+        assert!(writer.is_complete());
+        assert_eq!(b"7\r\npending\r\n0\r\n\r\n", body_of(&writer.into_inner()));
+
+        // trailers already written: only the final CRLF is missing
+        let mut writer = Writer::new(Vec::new());
+        let mut headers = Headers::new();
+        headers.typed_insert(TransferEncoding::chunked());
+        headers.insert(HeaderName::TRAILER, "X-Checksum");
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        writer.set_trailers_accepted(true);
+        writer.write_chunked_body_done().await.unwrap();
+        let mut trailers = Headers::new();
+        trailers.insert("X-Checksum", "1");
+        writer.write_trailers(&trailers).await.unwrap();
+        writer.complete().await.unwrap();
+        assert_eq!(b"0\r\nX-Checksum: 1\r\n\r\n", body_of(&writer.into_inner()));
+    }
+
+    async fn writer_declaring(trailer: &str) -> Writer<Vec<u8>> {
+        let mut writer = Writer::new(Vec::new());
+        let mut headers = Headers::new();
+        headers.typed_insert(TransferEncoding::chunked());
+        headers.insert(HeaderName::TRAILER, trailer);
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        writer.write_chunked_body_done().await.unwrap();
+        writer
+    }
+
+    fn trailers(fields: &[(&'static str, &str)]) -> Headers {
+        let mut headers = Headers::new();
+        for (name, value) in fields {
+            headers.append(*name, *value);
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_trailers_must_be_declared() {
+        let mut writer = writer_declaring("X-Checksum, x-count").await;
+        writer.set_trailers_accepted(true);
+        assert!(writer.write_trailers(&trailers(&[("X-Other", "1")])).await.is_err());
+        writer.write_trailers(&trailers(&[("X-Count", "2"), ("x-checksum", "abc")])).await.unwrap();
+        writer.finish().await.unwrap();
+        assert_eq!(b"0\r\nX-Count: 2\r\nx-checksum: abc\r\n\r\n", body_of(&writer.into_inner()));
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_trailers_rejected() {
+        let mut writer = writer_declaring("Content-Length, Host, If-Match, X-Ok").await;
+        writer.set_trailers_accepted(true);
+        for name in ["Content-Length", "host", "If-Match"] {
+            let fields = [(name, "1")];
+            assert!(writer.write_trailers(&trailers(&fields)).await.is_err(), "{}", name);
+        }
+        writer.write_trailers(&trailers(&[("X-Ok", "1")])).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trailers_need_te_or_force() {
+        let mut writer = writer_declaring("X-Checksum").await;
+        assert!(!writer.trailers_accepted());
+        assert!(writer.write_trailers(&trailers(&[("X-Checksum", "abc")])).await.is_err());
+
+        writer.force_trailers();
+        writer.write_trailers(&trailers(&[("X-Checksum", "abc")])).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_boxed_writer_over_duplex() {
+        use tokio::io::AsyncReadExt;
+
+        let (client, server) = tokio::io::duplex(1024);
+        let mut writer: Writer = Writer::new(Box::new(server));
+        writer.write_status_line(StatusCode::StatusNoContent).await.unwrap();
+        writer.write_headers(&Response::get_default_headers(0)).await.unwrap();
+        drop(writer);
+
+        let mut received = String::new();
+        let mut client = client;
+        client.read_to_string(&mut received).await.unwrap();
+        assert!(received.starts_with("HTTP/1.1 204 No Content\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_mixed_case_header_helpers() {
+        let mut response = Response::new(StatusCode::StatusOk, b"hi".to_vec());
+        response.headers.insert("content-type".to_string(), "text/html".to_string());
+        response.set_body(b"hello".to_vec());
+        assert_eq!(2, response.headers.len());
+        assert_eq!(Some("5"), response.headers.get_str("CONTENT-LENGTH"));
+        assert_eq!(Some("text/html"), response.headers.get_str("Content-Type"));
+
+        // the chunked handlers in the binary drop the default length this way
+        let mut headers = Response::get_default_headers(0);
+        headers.remove_entry("content-length");
+        headers.insert("transfer-encoding".to_string(), "chunked".to_string());
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&headers).await.unwrap();
+        let written = String::from_utf8(writer.into_inner()).unwrap().to_ascii_lowercase();
+        assert!(!written.contains("content-length"));
+        assert_eq!(1, written.matches("transfer-encoding: chunked").count());
+    }
+
+    #[tokio::test]
+    async fn test_send_built_response() {
+        let response = Response::builder()
+            .status(StatusCode::StatusCreated)
+            .typed_header(ContentType::json())
+            .header("Content-Length", "999")
+            .header("Set-Cookie", "a=1")
+            .header("Set-Cookie", "b=2")
+            .body("{}");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.send(response).await.unwrap();
+        assert!(writer.is_complete());
+        assert!(writer.keep_alive());
+
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!("HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: 2\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\n\r\n{}", written);
+    }
+
+    #[tokio::test]
+    async fn test_send_bodiless_status() {
+        let response = Response::builder()
+            .status(StatusCode::StatusNoContent)
+            .body("ignored");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.send(response).await.unwrap();
+        assert!(writer.is_complete());
+        assert!(writer.keep_alive());
+        assert_eq!(b"HTTP/1.1 204 No Content\r\n\r\n", writer.get_ref().as_slice());
+
+        // the default headers of Response::new carry a Content-Length
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.send(Response::new(StatusCode::StatusNoContent, "")).await.unwrap();
+        assert!(writer.is_complete());
+        assert!(!String::from_utf8_lossy(writer.get_ref()).contains("Content-Length"));
+
+        // a 304 may still describe the representation's length
+        let response = Response::builder()
+            .status(StatusCode::StatusNotModified)
+            .header("Content-Length", "42")
+            .body("");
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.send(response).await.unwrap();
+        assert!(String::from_utf8_lossy(writer.get_ref()).contains("Content-Length: 42\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_send_streaming_body_is_chunked() {
+        let response = Response::builder()
+            .header("Content-Length", "3")
+            .body(Body::from_reader(&b"hello world"[..]));
+
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.send(response).await.unwrap();
+        assert!(writer.is_complete());
+
+        let written = writer.into_inner();
+        let head = String::from_utf8_lossy(&written);
+        assert!(head.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+        assert_eq!(b"B\r\nhello world\r\n0\r\n\r\n", body_of(&written));
+    }
+
+    #[tokio::test]
+    async fn test_send_sized_reader_uses_content_length() {
+        let response = Response::builder().body(Body::from_sized_reader(&b"hello world"[..], 5));
+
+        let mut writer = Writer::new(Vec::new());
+        writer.send(response).await.unwrap();
+        let written = writer.into_inner();
+        assert!(String::from_utf8_lossy(&written).contains("Content-Length: 5\r\n"));
+        assert_eq!(b"hello", body_of(&written));
+
+        // a reader that comes up short can't be framed by the promised length
+        let response = Response::builder().body(Body::from_sized_reader(&b"hi"[..], 5));
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        assert!(writer.send(response).await.is_err());
+        assert!(!writer.keep_alive());
+    }
+
+    #[tokio::test]
+    async fn test_informational_before_final_response() {
+        let mut hints = Headers::new();
+        hints.append("Link", "</style.css>; rel=preload; as=style");
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_informational(StatusCode::StatusEarlyHints, &hints).await.unwrap();
+        writer.send(Response::new(StatusCode::StatusOk, "hi")).await.unwrap();
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(written.starts_with("HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\nHTTP/1.1 200 OK\r\n"));
+
+        let mut writer = Writer::new(Vec::new());
+        assert!(writer.write_informational(StatusCode::StatusOk, &Headers::new()).await.is_err());
+        assert!(writer.write_informational(StatusCode::StatusSwitchingProtocols, &Headers::new()).await.is_err());
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        assert!(writer.write_continue().await.is_err());
+
+        // HTTP/1.0 clients never see interim responses
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http10, false);
+        writer.write_continue().await.unwrap();
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_head_response_drops_body() {
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.set_head_request(true);
+        writer.write_status_line(StatusCode::StatusOk).await.unwrap();
+        writer.write_headers(&Response::get_default_headers(5)).await.unwrap();
+        assert_eq!(5, writer.write_body(b"hello").await.unwrap());
+        assert!(writer.is_complete());
+        assert!(writer.keep_alive());
+
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(written.contains("Content-Length: 5\r\n"));
+        assert!(written.ends_with("\r\n\r\n"));
+
+        // a chunked HEAD response keeps its header but sends no chunks
+        let mut writer = Writer::new(Vec::new());
+        writer.reset(Version::Http11, true);
+        writer.set_head_request(true);
+        writer.send(Response::builder().body(Body::from_reader(&b"streamed"[..]))).await.unwrap();
+        assert!(writer.is_complete());
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(written.ends_with("Transfer-Encoding: chunked\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_status_code_round_trip() {
+        for code in 100..600 {
+            if let Some(status) = StatusCode::from_u16(code) {
+                assert_eq!(code, status.as_u16());
+            }
+        }
+        assert_eq!(Some(StatusCode::StatusNotFound), StatusCode::from_u16(404));
+        assert_eq!(Some(StatusCode::StatusEarlyHints), StatusCode::from_u16(103));
+        assert_eq!(None, StatusCode::from_u16(418));
+        assert_eq!(None, StatusCode::from_u16(999));
+        assert_eq!(Err(306), StatusCode::try_from(306));
+    }
+
+    #[test]
+    fn test_reason_phrases() {
+        assert_eq!("Not Found", StatusCode::StatusNotFound.reason_phrase());
+        assert_eq!("HTTP Version Not Supported", StatusCode::StatusHttpVersionNotSupported.reason_phrase());
+        assert_eq!("404 Not Found", StatusCode::StatusNotFound.to_string());
+    }
+
+    #[test]
+    fn test_status_classes() {
+        assert!(StatusCode::StatusContinue.is_informational());
+        assert!(StatusCode::StatusNoContent.is_success());
+        assert!(StatusCode::StatusPermanentRedirect.is_redirection());
+        assert!(StatusCode::StatusTooManyRequests.is_client_error());
+        assert!(!StatusCode::StatusTooManyRequests.is_server_error());
+        assert!(StatusCode::StatusBadGateway.is_server_error());
+    }
+}
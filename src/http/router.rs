@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::http::headers::HeaderName;
+use crate::http::method::Method;
+use crate::http::request::Request;
+use crate::http::response::{Response, StatusCode, Writer};
+use crate::http::server::{Handler, ServerError};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Object-safe twin of `Handler` so routes with different handler types can
+/// live in the same table.
+trait ErasedHandler: Send + Sync {
+    fn call<'a>(&'a self, writer: &'a mut Writer, request: &'a mut Request) -> BoxFuture<'a, Result<(), ServerError>>;
+    fn call_expect_continue<'a>(&'a self, writer: &'a mut Writer, request: &'a mut Request) -> BoxFuture<'a, Result<(), ServerError>>;
+}
+
+impl<H: Handler> ErasedHandler for H {
+    fn call<'a>(&'a self, writer: &'a mut Writer, request: &'a mut Request) -> BoxFuture<'a, Result<(), ServerError>> {
+        Box::pin(self.handle(writer, request))
+    }
+
+    fn call_expect_continue<'a>(&'a self, writer: &'a mut Writer, request: &'a mut Request) -> BoxFuture<'a, Result<(), ServerError>> {
+        Box::pin(self.expect_continue(writer, request))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+struct Route {
+    method: Option<Method>,
+    segments: Vec<Segment>,
+    handler: Box<dyn ErasedHandler>,
+}
+
+fn parse_pattern(pattern: &str) -> (Option<Method>, Vec<Segment>) {
+    let (method, path) = match pattern.trim().split_once(' ') {
+        Some((method, path)) => (Some(method.parse().expect("invalid method in route pattern")), path.trim()),
+        None => (None, pattern.trim()),
+    };
+
+    let segments = path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if let Some(name) = s.strip_prefix(':') {
+                Segment::Param(name.to_string())
+            } else if let Some(name) = s.strip_prefix('*') {
+                Segment::Wildcard(name.to_string())
+            } else {
+                Segment::Static(s.to_string())
+            }
+        })
+        .collect();
+
+    (method, segments)
+}
+
+fn match_path(segments: &[Segment], path: &str) -> Option<HashMap<String, String>> {
+    let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let mut params = HashMap::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Static(s) => {
+                if parts.get(i) != Some(&s.as_str()) {
+                    return None;
+                }
+            },
+            Segment::Param(name) => {
+                let part = parts.get(i)?;
+                params.insert(name.clone(), part.to_string());
+            },
+            Segment::Wildcard(name) => {
+                let rest = parts.get(i..).unwrap_or_default().join("/");
+                params.insert(name.clone(), rest);
+                return Some(params);
+            },
+        }
+    }
+
+    if parts.len() == segments.len() {
+        Some(params)
+    } else {
+        None
+    }
+}
+
+/// Dispatches requests to handlers registered against path patterns.
+///
+/// Patterns are an optional method followed by a path, e.g. `GET /users/:id`
+/// or `/static/*path`. `:name` captures one segment and `*name` captures the
+/// rest of the path; captures are available through `Request::param`. Routes
+/// are tried in registration order. `GET` routes also answer `HEAD`.
+///
+/// `route` panics if the pattern's method is not a valid token.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router { routes: Vec::new() }
+    }
+
+    pub fn route<H: Handler>(&mut self, pattern: &str, handler: H) -> &mut Self {
+        let (method, segments) = parse_pattern(pattern);
+        self.routes.push(Route {
+            method,
+            segments,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Finds the route for the request and stores its path captures on it.
+    /// Without one, returns the methods the path would have accepted.
+    fn find_route(&self, request: &mut Request) -> Result<&Route, Vec<&str>> {
+        let path = request.target().path();
+
+        let mut allowed: Vec<&str> = Vec::new();
+
+        for route in &self.routes {
+            let Some(params) = match_path(&route.segments, &path) else {
+                continue;
+            };
+
+            match &route.method {
+                // every GET route answers HEAD too; the Writer drops the body
+                Some(Method::Get) if *request.method() == Method::Head => {
+                    request.set_params(params);
+                    return Ok(route);
+                },
+                Some(method) if method != request.method() => {
+                    if !allowed.contains(&method.as_str()) {
+                        allowed.push(method.as_str());
+                        if *method == Method::Get && !allowed.contains(&"HEAD") {
+                            allowed.push("HEAD");
+                        }
+                    }
+                },
+                _ => {
+                    request.set_params(params);
+                    return Ok(route);
+                },
+            }
+        }
+        Err(allowed)
+    }
+
+    async fn write_not_found(writer: &mut Writer, allowed: Vec<&str>) -> Result<(), ServerError> {
+        if allowed.is_empty() {
+            Self::write_error(writer, StatusCode::StatusNotFound, "Not Found", None).await
+        } else {
+            Self::write_error(writer, StatusCode::StatusMethodNotAllowed, "Method Not Allowed", Some(allowed.join(", "))).await
+        }
+    }
+
+    async fn write_error(writer: &mut Writer, status_code: StatusCode, message: &str, allow: Option<String>) -> Result<(), ServerError> {
+        let mut response = Response::new(status_code, message.as_bytes().to_vec());
+        if let Some(allow) = allow {
+            response.headers.insert(HeaderName::ALLOW, allow);
+        }
+        writer.send(response).await?;
+        Ok(())
+    }
+}
+
+impl Handler for Router {
+    async fn handle(&self, writer: &mut Writer, request: &mut Request) -> Result<(), ServerError> {
+        match self.find_route(request) {
+            Ok(route) => route.handler.call(writer, request).await,
+            Err(allowed) => Self::write_not_found(writer, allowed).await,
+        }
+    }
+
+    /// Asks the matched route; requests that will get a 404 or 405 anyway
+    /// are answered before the client sends the body.
+    async fn expect_continue(&self, writer: &mut Writer, request: &mut Request) -> Result<(), ServerError> {
+        match self.find_route(request) {
+            Ok(route) => route.handler.call_expect_continue(writer, request).await,
+            Err(allowed) => Self::write_not_found(writer, allowed).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+        let (_, segments) = parse_pattern(pattern);
+        match_path(&segments, path)
+    }
+
+    #[test]
+    fn test_parse_pattern_with_method() {
+        let (method, segments) = parse_pattern("GET /users/:id");
+        assert_eq!(Some(Method::Get), method);
+        assert_eq!(vec![
+            Segment::Static("users".to_string()),
+            Segment::Param("id".to_string()),
+        ], segments);
+    }
+
+    #[test]
+    fn test_parse_pattern_without_method() {
+        let (method, segments) = parse_pattern("/static/*path");
+        assert_eq!(None, method);
+        assert_eq!(vec![
+            Segment::Static("static".to_string()),
+            Segment::Wildcard("path".to_string()),
+        ], segments);
+    }
+
+    #[test]
+    fn test_match_static() {
+        assert!(params("/", "/").is_some());
+        assert!(params("/video", "/video").is_some());
+        assert!(params("/video", "/videos").is_none());
+        assert!(params("/video", "/video/extra").is_none());
+    }
+
+    #[test]
+    fn test_match_named_param() {
+        let p = params("/users/:id", "/users/42").unwrap();
+        assert_eq!("42", p.get("id").unwrap());
+        assert!(params("/users/:id", "/users").is_none());
+    }
+
+    #[test]
+    fn test_match_wildcard() {
+        let p = params("/static/*path", "/static/css/site.css").unwrap();
+        assert_eq!("css/site.css", p.get("path").unwrap());
+
+        let p = params("/static/*path", "/static").unwrap();
+        assert_eq!("", p.get("path").unwrap());
+
+        assert!(params("/static/*path", "/other/site.css").is_none());
+    }
+}
@@ -0,0 +1,780 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::net::TcpListener;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::http::method::Method;
+use crate::http::request::{BoxedRead, ParseError, ParseErrorKind, ParserLimits, Request, RequestReader};
+use crate::http::response::{Response, StatusCode, Writer};
+use crate::http::version::Version;
+
+#[derive(Debug)]
+pub enum ServerError{
+    BindError(std::io::Error),
+    ConnectionError(std::io::Error),
+    HandlerError { status_code: StatusCode, message: String },
+    /// Any other failure from inside a handler, answered with a 500.
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerError::BindError(e) => write!(f,"Failed to bind to address: {}", e),
+            ServerError::ConnectionError(e) => write!(f, "Connection error: {}", e),
+            ServerError::HandlerError{status_code, message} => write!(f, "Handler error: {} - {}", status_code, message),
+            ServerError::Other(e) => write!(f, "Handler failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServerError::BindError(e) | ServerError::ConnectionError(e) => Some(e),
+            ServerError::Other(e) => Some(e.as_ref()),
+            ServerError::HandlerError { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ServerError {
+    fn from(error: std::io::Error) -> Self {
+        ServerError::ConnectionError(error)
+    }
+}
+
+impl ServerError {
+    pub fn bad_request(message: &str) -> Self {
+        ServerError::HandlerError {
+            status_code: StatusCode::StatusBadRequest,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn internal_error(message: &str) -> Self {
+        ServerError::HandlerError {
+            status_code: StatusCode::StatusInternalServerError,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Answers a single parsed request by writing a response through the `Writer`.
+///
+/// Implementors can use `async fn handle(...)` directly; the returned future
+/// must be `Send` so the server can drive it on a spawned task.
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, writer: &mut Writer, request: &mut Request) -> impl Future<Output = Result<(), ServerError>> + Send;
+
+    /// Called before the body of a request sent with `Expect: 100-continue`
+    /// is read. Returning `Ok` without writing a response sends `100 Continue`,
+    /// then the body is read and `handle` called. An error (e.g. 417 or 413) or
+    /// a response written here is final instead: the body is never read and
+    /// the connection is closed. Interim responses such as `103 Early Hints`
+    /// can be sent either way.
+    fn expect_continue(&self, _writer: &mut Writer, _request: &mut Request) -> impl Future<Output = Result<(), ServerError>> + Send {
+        async { Ok(()) }
+    }
+}
+
+/// How long a closing connection keeps draining client input.
+const LINGER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connection-level settings for persistent (keep-alive) connections.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerConfig {
+    /// How long to wait for the next request on an open connection.
+    pub idle_timeout: Duration,
+    /// Requests served on one connection before it is closed.
+    pub max_requests_per_connection: usize,
+    /// Limits applied while parsing each request.
+    pub parser_limits: ParserLimits,
+    /// Bytes of chunked response data to coalesce before sending a chunk; see
+    /// `Writer::set_chunk_buffer_size`.
+    pub chunk_buffer_size: usize,
+    /// Call handlers as soon as the headers are in and let them read the body
+    /// with `Request::next_body_chunk`, instead of buffering it first. Whatever
+    /// a handler leaves unread is discarded before the next request.
+    pub stream_request_bodies: bool,
+    /// Called with errors nobody else gets to see: handler errors that were
+    /// answered with an error response and, for connections accepted by
+    /// `Server::serve`, whatever `serve_connection` returned.
+    pub on_error: Option<fn(&ServerError)>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            idle_timeout: Duration::from_secs(5),
+            max_requests_per_connection: 100,
+            parser_limits: ParserLimits::default(),
+            chunk_buffer_size: 0,
+            stream_request_bodies: false,
+            on_error: None,
+        }
+    }
+}
+
+pub struct Server {
+    listener: Arc<TcpListener>,
+    is_closed: Arc<AtomicBool>,
+}
+
+impl Server {
+
+    pub async fn serve<H: Handler>(port: u16, handler: H) -> Result<Server, ServerError> {
+        Self::serve_with_config(port, handler, ServerConfig::default()).await
+    }
+
+    pub async fn serve_with_config<H: Handler>(port: u16, handler: H, config: ServerConfig) -> Result<Server, ServerError> {
+        let addr = format!("127.0.0.1:{}", port);
+        let listener = Arc::new(TcpListener::bind(&addr).await
+            .map_err(ServerError::BindError)?);
+        let is_closed = Arc::new(AtomicBool::new(false));
+
+        let server = Server {
+            listener: listener.clone(),
+            is_closed: is_closed.clone(),
+        };
+
+        server.start_listening(Arc::new(handler), config);
+
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> Result<std::net::SocketAddr, ServerError> {
+        self.listener.local_addr().map_err(ServerError::ConnectionError)
+    }
+
+    fn start_listening<H: Handler>(&self, handler: Arc<H>, config: ServerConfig) {
+        let listener = self.listener.clone();
+        let is_closed = self.is_closed.clone();
+
+        tokio::spawn(async move {
+            Self::listen_loop(listener, is_closed, handler, config).await;
+        });
+    }
+
+    async fn listen_loop<H: Handler>(listener: Arc<TcpListener>, is_closed: Arc<AtomicBool>, handler: Arc<H>, config: ServerConfig) {
+        loop {
+            if is_closed.load(Ordering::Relaxed) {
+                break;
+            }
+
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let handler = handler.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = Self::serve_connection(stream, handler.as_ref(), config).await {
+                            Self::report_error(&config, &e);
+                        }
+                    });
+                },
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Runs the request/response loop over an already-established connection.
+    ///
+    /// `serve` calls this for every accepted TCP stream; call it directly to
+    /// serve other transports such as TLS streams or Unix sockets.
+    pub async fn serve_connection<S, H>(stream: S, handler: &H, config: ServerConfig) -> Result<(), ServerError>
+        where S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+              H: Handler
+    {
+        let (read_half, write_half) = tokio::io::split(stream);
+        let mut reader = RequestReader::with_limits(Box::new(read_half) as BoxedRead, config.parser_limits);
+        let mut writer: Writer = Writer::new(Box::new(write_half));
+        writer.set_chunk_buffer_size(config.chunk_buffer_size);
+
+        let result = Self::connection_loop(&mut reader, &mut writer, handler, config).await;
+
+        // Half-close, then drain whatever the client still sends. Closing a socket
+        // with unread input makes the kernel send a RST, which can destroy the
+        // response we just wrote before the client reads it.
+        let _ = writer.shutdown().await;
+        let mut reader = reader.into_inner();
+        let _ = tokio::time::timeout(LINGER_TIMEOUT, async {
+            let mut buf = [0u8; 1024];
+            while let Ok(n) = reader.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+            }
+        }).await;
+
+        result
+    }
+
+    async fn connection_loop<H: Handler>(reader: &mut RequestReader<BoxedRead>, writer: &mut Writer, handler: &H, config: ServerConfig) -> Result<(), ServerError> {
+        let mut requests_served = 0;
+
+        loop {
+            let mut request = match tokio::time::timeout(config.idle_timeout, reader.next_request_head()).await {
+                Err(_) => break, // idle timeout
+                Ok(Err(e)) if e == ParseErrorKind::ConnectionClosed => break,
+                Ok(Err(e)) => return Self::reject_request(writer, e).await,
+                Ok(Ok(request)) => request,
+            };
+            requests_served += 1;
+
+            let keep_alive = request.keep_alive() && requests_served < config.max_requests_per_connection;
+
+            // a response sent before the body is read leaves it unread on the
+            // stream, so the connection can't be reused afterwards
+            if let Some(expectation) = request.unsupported_expectation() {
+                let message = format!("unsupported expectation: {}", expectation);
+                Self::prepare_writer(writer, &request, false);
+                Self::write_error_response(writer, StatusCode::StatusExpectationFailed, &message).await?;
+                break;
+            }
+            if request.expects_continue() && !request.is_complete() {
+                Self::prepare_writer(writer, &request, false);
+                match handler.expect_continue(writer, &mut request).await {
+                    Ok(()) if !writer.has_started() => writer.write_continue().await?,
+                    Ok(()) => {
+                        writer.complete().await?;
+                        break;
+                    },
+                    Err(e) if writer.has_started() => return Err(e),
+                    Err(e) => {
+                        let (status_code, message) = Self::error_status(&e);
+                        Self::write_error_response(writer, status_code, &message).await?;
+                        break;
+                    },
+                }
+            }
+
+            if config.stream_request_bodies {
+                // lend the handler the connection to read the body from
+                let placeholder = RequestReader::new(Box::new(tokio::io::empty()) as BoxedRead);
+                request.set_body_source(std::mem::replace(reader, placeholder));
+            } else {
+                match tokio::time::timeout(config.idle_timeout, reader.read_body(&mut request)).await {
+                    Err(_) => break,
+                    Ok(Err(e)) => return Self::reject_request(writer, e).await,
+                    Ok(Ok(())) => {},
+                }
+            }
+
+            Self::prepare_writer(writer, &request, keep_alive);
+
+            let result = match handler.handle(writer, &mut request).await {
+                // the client is owed a response even if the handler forgot to write one
+                Ok(()) if !writer.has_started() => {
+                    Self::write_error_response(writer, StatusCode::StatusInternalServerError, "Internal Server Error").await
+                },
+                // a handler that returns without ending its chunked body still gets a valid one
+                Ok(()) => writer.complete().await.map_err(ServerError::from),
+                Err(e) => Err(e),
+            };
+
+            // the next request starts where this body ends, so skip whatever
+            // the handler didn't read
+            let body_consumed = request.is_complete() || tokio::time::timeout(config.idle_timeout, async {
+                while request.next_body_chunk().await?.is_some() {}
+                Ok::<_, std::io::Error>(())
+            }).await.is_ok_and(|r| r.is_ok());
+            if let Some(source) = request.take_body_source() {
+                *reader = source;
+            }
+            if let Err(e) = result {
+                if writer.has_started() {
+                    // half a response is already on the wire, nothing to do but hang up
+                    return Err(e);
+                }
+                Self::report_error(&config, &e);
+
+                let (status_code, message) = Self::error_status(&e);
+                // a failed non-idempotent request may have been partly applied; close so
+                // nothing pipelined behind it runs against that state (RFC 9112 section 9.3.2)
+                writer.reset(request.version(), keep_alive && body_consumed && request.method().is_idempotent());
+                Self::write_error_response(writer, status_code, &message).await?;
+            }
+
+            if !body_consumed || !writer.keep_alive() || !writer.is_complete() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn report_error(config: &ServerConfig, error: &ServerError) {
+        if let Some(on_error) = config.on_error {
+            on_error(error);
+        }
+    }
+
+    fn prepare_writer(writer: &mut Writer, request: &Request, keep_alive: bool) {
+        writer.reset(request.version(), keep_alive);
+        writer.set_trailers_accepted(request.accepts_trailers());
+        writer.set_head_request(*request.method() == Method::Head);
+    }
+
+    /// Answers a request that couldn't be parsed. The stream can't be trusted
+    /// past it, so the connection is closed afterwards.
+    async fn reject_request(writer: &mut Writer, error: ParseError) -> Result<(), ServerError> {
+        if error == ParseErrorKind::Io {
+            return Err(ServerError::ConnectionError(error.into()));
+        }
+        let status_code = error.status_code();
+        writer.reset(Version::Http11, false);
+        Self::write_error_response(writer, status_code, &error.to_string()).await?;
+        Err(ServerError::HandlerError { status_code, message: error.to_string() })
+    }
+
+    fn error_status(error: &ServerError) -> (StatusCode, String) {
+        match error {
+            ServerError::HandlerError { status_code, message } => (*status_code, message.clone()),
+            // a streamed body that failed to decode while the handler read it
+            ServerError::ConnectionError(e) => match e.get_ref().and_then(|e| e.downcast_ref::<ParseError>()) {
+                Some(parse_error) => (parse_error.status_code(), parse_error.to_string()),
+                None => (StatusCode::StatusInternalServerError, "Internal Server Error".to_string()),
+            },
+            _ => (StatusCode::StatusInternalServerError, "Internal Server Error".to_string()),
+        }
+    }
+
+    async fn write_error_response(writer: &mut Writer, status_code: StatusCode, message: &str) -> Result<(), ServerError> {
+        writer.send(Response::new(status_code, message.as_bytes().to_vec())).await?;
+        Ok(())
+    }
+
+    pub async fn close(self) -> Result<(), ServerError> {
+        self.is_closed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::headers::Headers;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpStream;
+
+    struct Hello;
+
+    impl Handler for Hello {
+        async fn handle(&self, writer: &mut Writer, _request: &mut Request) -> Result<(), ServerError> {
+            writer.write_status_line(StatusCode::StatusOk).await?;
+            writer.write_headers(&Response::get_default_headers(5)).await?;
+            writer.write_body(b"hello").await?;
+            Ok(())
+        }
+    }
+
+    async fn read_response(stream: &mut TcpStream) -> String {
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).to_string()
+    }
+
+    struct Chunked;
+
+    impl Handler for Chunked {
+        async fn handle(&self, writer: &mut Writer, _request: &mut Request) -> Result<(), ServerError> {
+            writer.write_status_line(StatusCode::StatusOk).await?;
+            let mut headers = Response::get_default_headers(0);
+            headers.remove_entry("Content-Length");
+            headers.insert("Transfer-Encoding".to_string(), "chunked".to_string());
+            writer.write_headers(&headers).await?;
+            writer.write_chunked_body(b"hel").await?;
+            writer.write_chunked_body(b"lo").await?;
+            writer.write_chunked_body_done().await?;
+            writer.finish().await?;
+            Ok(())
+        }
+    }
+
+    /// Starts a chunked body and returns without ending it.
+    struct Unfinished;
+
+    impl Handler for Unfinished {
+        async fn handle(&self, writer: &mut Writer, _request: &mut Request) -> Result<(), ServerError> {
+            writer.write_status_line(StatusCode::StatusOk).await?;
+            let mut headers = Response::get_default_headers(0);
+            headers.remove_entry("Content-Length");
+            headers.insert("Transfer-Encoding".to_string(), "chunked".to_string());
+            writer.write_headers(&headers).await?;
+            writer.write_chunked_body(b"partial").await?;
+            Ok(())
+        }
+    }
+
+    struct Silent;
+
+    impl Handler for Silent {
+        async fn handle(&self, _writer: &mut Writer, _request: &mut Request) -> Result<(), ServerError> {
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl Handler for Failing {
+        async fn handle(&self, _writer: &mut Writer, _request: &mut Request) -> Result<(), ServerError> {
+            Err(ServerError::bad_request("no thanks"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_error_keeps_idempotent_connection() {
+        let server = Server::serve(0, Failing).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"PUT / HTTP/1.1\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.ends_with("no thanks"));
+        assert!(!response.contains("Connection: close"));
+
+        stream.write_all(b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.contains("Connection: close"));
+        assert_eq!("", read_response(&mut stream).await);
+    }
+
+    #[tokio::test]
+    async fn test_handler_errors_reach_on_error() {
+        static REPORTED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        let config = ServerConfig {
+            on_error: Some(|e| REPORTED.lock().unwrap().push(e.to_string())),
+            ..ServerConfig::default()
+        };
+
+        let (mut client, server) = tokio::io::duplex(4096);
+        let connection = tokio::spawn(async move {
+            Server::serve_connection(server, &Failing, config).await
+        });
+
+        client.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(connection.await.unwrap().is_ok());
+        assert_eq!(vec!["Handler error: 400 Bad Request - no thanks"], *REPORTED.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_handler_that_writes_nothing_gets_500() {
+        let (mut client, server) = tokio::io::duplex(4096);
+        let connection = tokio::spawn(async move {
+            Server::serve_connection(server, &Silent, ServerConfig::default()).await
+        });
+
+        client.write_all(b"GET /1 HTTP/1.1\r\n\r\nGET /2 HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+
+        assert_eq!(2, response.matches("HTTP/1.1 500 Internal Server Error\r\n").count());
+        drop(client);
+        assert!(connection.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_serve_connection_over_duplex() {
+        let (mut client, server) = tokio::io::duplex(4096);
+        let connection = tokio::spawn(async move {
+            Server::serve_connection(server, &Hello, ServerConfig::default()).await
+        });
+
+        client.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("hello"));
+        drop(client);
+        assert!(connection.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_serves_multiple_requests() {
+        let server = Server::serve(0, Hello).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let first = read_response(&mut stream).await;
+        assert!(first.starts_with("HTTP/1.1 200 OK"));
+        assert!(!first.contains("Connection: close"));
+
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let second = read_response(&mut stream).await;
+        assert!(second.starts_with("HTTP/1.1 200 OK"));
+        assert!(second.contains("Connection: close"));
+
+        // server hangs up after honouring the close
+        assert_eq!("", read_response(&mut stream).await);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests_answered_in_order() {
+        let server = Server::serve(0, Hello).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET /1 HTTP/1.1\r\nHost: localhost\r\n\r\nGET /2 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+
+        let mut responses = String::new();
+        stream.read_to_string(&mut responses).await.unwrap();
+        assert_eq!(2, responses.matches("HTTP/1.1 200 OK").count());
+        assert!(responses.ends_with("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_smuggling_attempt_gets_400() {
+        let server = Server::serve(0, Hello).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"POST / HTTP/1.1\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n").await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.contains("Connection: close"));
+    }
+
+    #[tokio::test]
+    async fn test_parser_limits_map_to_status_codes() {
+        let config = ServerConfig {
+            parser_limits: ParserLimits { max_request_line_len: 16, max_header_count: 1, max_body_size: 4, ..ParserLimits::default() },
+            ..ServerConfig::default()
+        };
+        let server = Server::serve_with_config(0, Hello, config).await.unwrap();
+
+        let cases: [(&[u8], &str); 4] = [
+            (b"GET /a/very/long/path HTTP/1.1\r\n\r\n", "HTTP/1.1 414 URI Too Long"),
+            (b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n", "HTTP/1.1 431 Request Header Fields Too Large"),
+            (b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello", "HTTP/1.1 413 Content Too Large"),
+            (b"GET / HTTP/2.0\r\n\r\n", "HTTP/1.1 505 HTTP Version Not Supported"),
+        ];
+
+        for (request, status_line) in cases {
+            let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+            stream.write_all(request).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with(status_line), "{}", response);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_10_response_version_and_close() {
+        let server = Server::serve(0, Hello).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(response.contains("Connection: close"));
+    }
+
+    #[tokio::test]
+    async fn test_http_10_keep_alive() {
+        let server = Server::serve(0, Hello).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(response.contains("Connection: keep-alive"));
+
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[tokio::test]
+    async fn test_http_10_chunked_response_is_close_delimited() {
+        let server = Server::serve(0, Chunked).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(!response.contains("Transfer-Encoding"));
+        assert!(response.contains("Connection: close"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn test_http_11_chunked_response() {
+        let server = Server::serve(0, Chunked).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.contains("Transfer-Encoding: chunked"));
+        assert!(response.ends_with("\r\n\r\n3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_chunked_body_is_terminated() {
+        let server = Server::serve(0, Unfinished).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET /1 HTTP/1.1\r\n\r\nGET /2 HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut responses = String::new();
+        stream.read_to_string(&mut responses).await.unwrap();
+        assert_eq!(2, responses.matches("7\r\npartial\r\n0\r\n\r\n").count());
+    }
+
+    #[tokio::test]
+    async fn test_head_gets_headers_without_body() {
+        let mut router = crate::http::Router::new();
+        router.route("GET /", Hello);
+        let server = Server::serve(0, router).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut responses = String::new();
+        stream.read_to_string(&mut responses).await.unwrap();
+
+        let (head, get) = responses.split_at(responses.rfind("HTTP/1.1").unwrap());
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("Content-Length: 5\r\n"));
+        assert!(head.ends_with("\r\n\r\n"));
+        assert!(get.ends_with("\r\n\r\nhello"));
+    }
+
+    /// Takes uploads except to `/full`, hinting at a stylesheet either way.
+    struct Uploads;
+
+    impl Handler for Uploads {
+        async fn handle(&self, writer: &mut Writer, request: &mut Request) -> Result<(), ServerError> {
+            Hello.handle(writer, request).await
+        }
+
+        async fn expect_continue(&self, writer: &mut Writer, request: &mut Request) -> Result<(), ServerError> {
+            let mut hints = Headers::new();
+            hints.append("Link", "</style.css>; rel=preload");
+            writer.write_informational(StatusCode::StatusEarlyHints, &hints).await?;
+            if request.get_target() == "/full" {
+                return Err(ServerError::HandlerError { status_code: StatusCode::StatusContentTooLarge, message: "full".to_string() });
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_accepted() {
+        let server = Server::serve(0, Uploads).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"POST /upload HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n").await.unwrap();
+        let mut interim = Vec::new();
+        while !interim.ends_with(b"100 Continue\r\n\r\n") {
+            interim.push(stream.read_u8().await.unwrap());
+        }
+        assert_eq!(b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 100 Continue\r\n\r\n", interim.as_slice());
+
+        // the connection stays usable once the body has been read
+        stream.write_all(b"hello").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(!response.contains("Connection: close"));
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_rejected() {
+        let server = Server::serve(0, Uploads).await.unwrap();
+
+        let cases: [(&[u8], &str); 2] = [
+            (b"POST /full HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n", "HTTP/1.1 413 Content Too Large"),
+            (b"POST /upload HTTP/1.1\r\nContent-Length: 5\r\nExpect: x-fancy\r\n\r\n", "HTTP/1.1 417 Expectation Failed"),
+        ];
+
+        for (request, status_line) in cases {
+            let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+            stream.write_all(request).await.unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(!response.contains("100 Continue"));
+            assert!(response.contains(status_line), "{}", response);
+            assert!(response.contains("Connection: close"));
+        }
+    }
+
+    /// Answers with the size of the body, read piece by piece.
+    struct Counter;
+
+    impl Handler for Counter {
+        async fn handle(&self, writer: &mut Writer, request: &mut Request) -> Result<(), ServerError> {
+            let mut total = 0;
+            while let Some(chunk) = request.next_body_chunk().await? {
+                total += chunk.len();
+            }
+            writer.send(Response::new(StatusCode::StatusOk, total.to_string())).await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_request_bodies() {
+        let config = ServerConfig { stream_request_bodies: true, ..ServerConfig::default() };
+        let counter = Server::serve_with_config(0, Counter, config).await.unwrap();
+        let mut stream = TcpStream::connect(counter.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n").await.unwrap();
+        stream.write_all(b"6\r\n world\r\n0\r\n\r\n").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\r\n\r\n11"));
+
+        // a body the handler never reads is skipped to get at the next request
+        let hello = Server::serve_with_config(0, Hello, config).await.unwrap();
+        let mut stream = TcpStream::connect(hello.local_addr().unwrap()).await.unwrap();
+        stream.write_all(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nworldGET / HTTP/1.1\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut responses = String::new();
+        stream.read_to_string(&mut responses).await.unwrap();
+        assert_eq!(2, responses.matches("HTTP/1.1 200 OK").count());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_body_too_large() {
+        let config = ServerConfig {
+            stream_request_bodies: true,
+            parser_limits: ParserLimits { max_body_size: 4, ..ParserLimits::default() },
+            ..ServerConfig::default()
+        };
+        let server = Server::serve_with_config(0, Counter, config).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"PUT / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Content Too Large"));
+        assert!(response.contains("Connection: close"));
+    }
+
+    #[tokio::test]
+    async fn test_max_requests_per_connection() {
+        let config = ServerConfig { max_requests_per_connection: 1, ..ServerConfig::default() };
+        let server = Server::serve_with_config(0, Hello, config).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let response = read_response(&mut stream).await;
+        assert!(response.contains("Connection: close"));
+        assert_eq!("", read_response(&mut stream).await);
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_closes_connection() {
+        let config = ServerConfig { idle_timeout: Duration::from_millis(50), ..ServerConfig::default() };
+        let server = Server::serve_with_config(0, Hello, config).await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+
+        assert_eq!("", read_response(&mut stream).await);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::http::request::{ParseError, ParseErrorKind};
+
+/// The four request-target forms from RFC 9112 section 3.2.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum TargetForm {
+    /// `/path?query`, used by almost every request.
+    #[default]
+    Origin,
+    /// `http://host/path?query`, sent to proxies.
+    Absolute,
+    /// `host:port`, only valid for CONNECT.
+    Authority,
+    /// `*`, only valid for a server-wide OPTIONS.
+    Asterisk,
+}
+
+/// Decoded query-string parameters. Keys may repeat, so lookups come in
+/// first-value and all-values flavours. Escapes that don't decode to UTF-8
+/// (e.g. latin-1 `%FF`) become U+FFFD; `RequestTarget::query_string` keeps
+/// the original bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn parse(query: &str) -> Result<Self, ParseError> {
+        let mut pairs = Vec::new();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            pairs.push((decode_query_component(key)?, decode_query_component(value)?));
+        }
+        Ok(Query(pairs))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.0.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A parsed request target.
+///
+/// The path is percent-decoded segment by segment and has its dot segments
+/// resolved, so `/static/../secret` comes out as `/secret` and can never climb
+/// above the root. Segments that decode to a `/` or NUL are rejected rather
+/// than risk being re-joined into a different path later.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RequestTarget {
+    raw: String,
+    form: TargetForm,
+    scheme: Option<String>,
+    authority: Option<String>,
+    segments: Vec<String>,
+    trailing_slash: bool,
+    query_string: Option<String>,
+    query: Query,
+}
+
+impl RequestTarget {
+    /// The target exactly as it appeared on the request line.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn form(&self) -> TargetForm {
+        self.form
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// Decoded, normalised path segments.
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// Decoded, normalised path, always starting with `/`.
+    pub fn path(&self) -> String {
+        let mut path = format!("/{}", self.segments.join("/"));
+        if self.trailing_slash && !self.segments.is_empty() {
+            path.push('/');
+        }
+        path
+    }
+
+    /// The undecoded query string, without the leading `?`.
+    pub fn query_string(&self) -> Option<&str> {
+        self.query_string.as_deref()
+    }
+
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    fn parse_path_and_query(&mut self, rest: &str) -> Result<(), ParseError> {
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        self.segments = normalize_segments(path)?;
+        self.trailing_slash = path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..");
+        if let Some(query) = query {
+            self.query = Query::parse(query)?;
+            self.query_string = Some(query.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for RequestTarget {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.contains('#') || s.bytes().any(|b| b.is_ascii_control() || b == b' ') {
+            return Err(invalid_target());
+        }
+
+        let mut target = RequestTarget {
+            raw: s.to_string(),
+            ..RequestTarget::default()
+        };
+
+        if s == "*" {
+            target.form = TargetForm::Asterisk;
+        } else if s.starts_with('/') {
+            target.form = TargetForm::Origin;
+            target.parse_path_and_query(s)?;
+        } else if let Some((scheme, rest)) = s.split_once("://") {
+            if !is_valid_scheme(scheme) {
+                return Err(invalid_target());
+            }
+            let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+            let (authority, path) = rest.split_at(authority_end);
+            if authority.is_empty() {
+                return Err(invalid_target());
+            }
+
+            target.form = TargetForm::Absolute;
+            target.scheme = Some(scheme.to_ascii_lowercase());
+            target.authority = Some(authority.to_string());
+            target.parse_path_and_query(path)?;
+        } else {
+            // authority-form is host ":" port, nothing else
+            let (host, port) = s.rsplit_once(':').ok_or_else(invalid_target)?;
+            if host.is_empty() || port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit()) || s.contains(['/', '?']) {
+                return Err(invalid_target());
+            }
+            target.form = TargetForm::Authority;
+            target.authority = Some(s.to_string());
+        }
+
+        Ok(target)
+    }
+}
+
+impl std::fmt::Display for RequestTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+fn invalid_target() -> ParseError {
+    ParseErrorKind::InvalidTarget.into()
+}
+
+fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Splits a path into decoded segments and applies RFC 3986 section 5.2.4
+/// dot-segment removal. Dots are matched after decoding, so `%2e%2e` is
+/// treated as `..` too.
+fn normalize_segments(path: &str) -> Result<Vec<String>, ParseError> {
+    let mut segments: Vec<String> = Vec::new();
+    for raw in path.split('/').filter(|s| !s.is_empty()) {
+        let segment = String::from_utf8(percent_decode(raw)?)
+            .map_err(|_| invalid_target())?;
+        if segment.contains(['/', '\0']) {
+            return Err(invalid_target());
+        }
+
+        match segment.as_str() {
+            "." => {},
+            ".." => { segments.pop(); },
+            _ => segments.push(segment),
+        }
+    }
+    Ok(segments)
+}
+
+fn decode_query_component(s: &str) -> Result<String, ParseError> {
+    let decoded = percent_decode(&s.replace('+', " "))?;
+    Ok(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+fn percent_decode(s: &str) -> Result<Vec<u8>, ParseError> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i+1..i+3)
+                .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(invalid_target)?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_origin_form() {
+        let target: RequestTarget = "/video?x=1".parse().unwrap();
+        assert_eq!(TargetForm::Origin, target.form());
+        assert_eq!("/video", target.path());
+        assert_eq!(Some("x=1"), target.query_string());
+        assert_eq!(Some("1"), target.query().get("x"));
+        assert_eq!("/video?x=1", target.raw());
+    }
+
+    #[test]
+    fn test_absolute_form() {
+        let target: RequestTarget = "HTTP://example.com:8080/a/b?c=d".parse().unwrap();
+        assert_eq!(TargetForm::Absolute, target.form());
+        assert_eq!(Some("http"), target.scheme());
+        assert_eq!(Some("example.com:8080"), target.authority());
+        assert_eq!("/a/b", target.path());
+        assert_eq!(Some("d"), target.query().get("c"));
+
+        let target: RequestTarget = "http://example.com".parse().unwrap();
+        assert_eq!("/", target.path());
+    }
+
+    #[test]
+    fn test_authority_and_asterisk_form() {
+        let target: RequestTarget = "example.com:443".parse().unwrap();
+        assert_eq!(TargetForm::Authority, target.form());
+        assert_eq!(Some("example.com:443"), target.authority());
+
+        let target: RequestTarget = "*".parse().unwrap();
+        assert_eq!(TargetForm::Asterisk, target.form());
+
+        assert!("example.com".parse::<RequestTarget>().is_err());
+        assert!("example.com:https".parse::<RequestTarget>().is_err());
+    }
+
+    #[test]
+    fn test_percent_decoded_segments() {
+        let target: RequestTarget = "/files/my%20file.txt".parse().unwrap();
+        assert_eq!(vec!["files", "my file.txt"], target.segments());
+        assert_eq!("/files/my file.txt", target.path());
+
+        assert!("/bad%zzescape".parse::<RequestTarget>().is_err());
+        assert!("/truncated%2".parse::<RequestTarget>().is_err());
+        assert!("/sneaky%2fslash".parse::<RequestTarget>().is_err());
+    }
+
+    #[test]
+    fn test_dot_segment_normalisation() {
+        let target: RequestTarget = "/static/./css/../../etc/passwd".parse().unwrap();
+        assert_eq!("/etc/passwd", target.path());
+
+        let target: RequestTarget = "/../../../etc/passwd".parse().unwrap();
+        assert_eq!("/etc/passwd", target.path());
+
+        let target: RequestTarget = "/static/%2e%2e/secret".parse().unwrap();
+        assert_eq!("/secret", target.path());
+
+        let target: RequestTarget = "/a/b/..".parse().unwrap();
+        assert_eq!("/a/", target.path());
+    }
+
+    #[test]
+    fn test_query_multimap() {
+        let target: RequestTarget = "/search?tag=rust&tag=http&q=hello+world&empty&name=caf%C3%A9".parse().unwrap();
+        let query = target.query();
+        assert_eq!(Some("rust"), query.get("tag"));
+        assert_eq!(vec!["rust", "http"], query.get_all("tag"));
+        assert_eq!(Some("hello world"), query.get("q"));
+        assert_eq!(Some(""), query.get("empty"));
+        assert_eq!(Some("café"), query.get("name"));
+        assert_eq!(5, query.len());
+    }
+
+    #[test]
+    fn test_non_utf8_query_value() {
+        // latin-1 "café" still parses; the raw query string is untouched
+        let target: RequestTarget = "/search?q=caf%E9&x=%FF".parse().unwrap();
+        assert_eq!(Some("caf\u{FFFD}"), target.query().get("q"));
+        assert_eq!(Some("\u{FFFD}"), target.query().get("x"));
+        assert_eq!(Some("q=caf%E9&x=%FF"), target.query_string());
+
+        // the path still has to be UTF-8
+        assert!("/caf%E9".parse::<RequestTarget>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_targets() {
+        assert!("".parse::<RequestTarget>().is_err());
+        assert!("/page#fragment".parse::<RequestTarget>().is_err());
+        assert!("relative/path".parse::<RequestTarget>().is_err());
+        assert!("1http://x/".parse::<RequestTarget>().is_err());
+    }
+}
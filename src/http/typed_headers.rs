@@ -0,0 +1,864 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::http::headers::{HeaderName, Headers};
+use crate::http::request::{ParseError, ParseErrorKind};
+
+/// A header with a structured value.
+///
+/// `decode` sees every field line with the header's name, in order, so list
+/// headers split across several lines decode the same as one joined line.
+pub trait TypedHeader: Sized {
+    const NAME: HeaderName;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError>;
+
+    fn encode(&self) -> String;
+}
+
+impl Headers {
+    /// Decodes a typed header, giving `None` when it is missing or malformed.
+    pub fn typed_get<T: TypedHeader>(&self) -> Option<T> {
+        self.typed_try_get().ok().flatten()
+    }
+
+    /// Like `typed_get` but tells a missing header apart from a malformed one.
+    pub fn typed_try_get<T: TypedHeader>(&self) -> Result<Option<T>, ParseError> {
+        if !self.contains_key(T::NAME) {
+            return Ok(None);
+        }
+        let values = self.get_all(T::NAME)
+            .map(|v| v.to_str())
+            .collect::<Result<Vec<_>, _>>()?;
+        T::decode(values.into_iter()).map(Some)
+    }
+
+    /// Replaces any existing values of the header with `header`.
+    pub fn typed_insert<T: TypedHeader>(&mut self, header: T) {
+        self.set(T::NAME, header.encode());
+    }
+}
+
+fn invalid(name: &HeaderName) -> ParseError {
+    ParseErrorKind::InvalidFormat(format!("invalid {} header", name.as_str().to_ascii_lowercase())).into()
+}
+
+/// Headers that may only appear once with a single value.
+fn single_value<'a, I: Iterator<Item = &'a str>>(name: &HeaderName, mut values: I) -> Result<&'a str, ParseError> {
+    match (values.next(), values.next()) {
+        (Some(value), None) => Ok(value.trim()),
+        _ => Err(invalid(name)),
+    }
+}
+
+/// Elements of a comma-separated list header, with empty elements dropped as
+/// RFC 9110 section 5.6.1 requires.
+fn list_values<'a, I: Iterator<Item = &'a str>>(values: I) -> impl Iterator<Item = &'a str> {
+    values.flat_map(|v| v.split(','))
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(Headers::is_valid_tchar)
+}
+
+/// `Content-Length`. A repeated field is accepted only when every value agrees.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ContentLength(pub usize);
+
+impl TypedHeader for ContentLength {
+    const NAME: HeaderName = HeaderName::CONTENT_LENGTH;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let mut content_length = None;
+        for part in values.flat_map(|v| v.split(',')).map(|p| p.trim()) {
+            // str::parse would also accept a leading '+'
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ParseErrorKind::InvalidContentLength.into());
+            }
+            let n = part.parse::<usize>().map_err(|_| ParseErrorKind::InvalidContentLength)?;
+            match content_length {
+                Some(existing) if existing != n => return Err(ParseErrorKind::ConflictingContentLength.into()),
+                _ => content_length = Some(n),
+            }
+        }
+        content_length.map(ContentLength).ok_or(ParseErrorKind::InvalidContentLength.into())
+    }
+
+    fn encode(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// `Content-Type`: a media type plus optional parameters such as `charset`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContentType {
+    mime_type: String,
+    params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    pub fn new(mime_type: &str) -> Self {
+        ContentType { mime_type: mime_type.to_ascii_lowercase(), params: Vec::new() }
+    }
+
+    pub fn text_plain() -> Self {
+        Self::new("text/plain")
+    }
+
+    pub fn html() -> Self {
+        Self::new("text/html")
+    }
+
+    pub fn json() -> Self {
+        Self::new("application/json")
+    }
+
+    pub fn octet_stream() -> Self {
+        Self::new("application/octet-stream")
+    }
+
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_ascii_lowercase(), value.to_string()));
+        self
+    }
+
+    /// The lowercased `type/subtype`, without parameters.
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+}
+
+impl TypedHeader for ContentType {
+    const NAME: HeaderName = HeaderName::CONTENT_TYPE;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let value = single_value(&Self::NAME, values)?;
+        let mut parts = value.split(';');
+        let mime_type = parts.next().unwrap_or_default().trim();
+        match mime_type.split_once('/') {
+            Some((ty, subtype)) if is_token(ty) && is_token(subtype) => {},
+            _ => return Err(invalid(&Self::NAME)),
+        }
+
+        let mut content_type = ContentType::new(mime_type);
+        for param in parts.map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let (name, value) = param.split_once('=').ok_or_else(|| invalid(&Self::NAME))?;
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            content_type = content_type.with_param(name.trim(), value);
+        }
+        Ok(content_type)
+    }
+
+    fn encode(&self) -> String {
+        let mut value = self.mime_type.clone();
+        for (name, param) in &self.params {
+            if is_token(param) {
+                value.push_str(&format!("; {}={}", name, param));
+            } else {
+                value.push_str(&format!("; {}=\"{}\"", name, param));
+            }
+        }
+        value
+    }
+}
+
+/// `Host`: the authority the request is aimed at.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Host {
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl TypedHeader for Host {
+    const NAME: HeaderName = HeaderName::HOST;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let value = single_value(&Self::NAME, values)?;
+        // an IPv6 literal carries its own colons inside the brackets
+        let port_sep = match value.rfind(']') {
+            Some(end) => value[end..].find(':').map(|i| end + i),
+            None => value.rfind(':'),
+        };
+        let (host, port) = match port_sep {
+            Some(i) => {
+                let port = value[i+1..].parse::<u16>().map_err(|_| invalid(&Self::NAME))?;
+                (&value[..i], Some(port))
+            },
+            None => (value, None),
+        };
+        if host.is_empty() || host.contains(['/', '?', '#', '@', ' ']) {
+            return Err(invalid(&Self::NAME));
+        }
+        Ok(Host { host: host.to_string(), port })
+    }
+
+    fn encode(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.clone(),
+        }
+    }
+}
+
+/// `Date`, sent in the IMF-fixdate format from RFC 9110 section 5.6.7.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Date(pub SystemTime);
+
+impl Date {
+    pub fn now() -> Self {
+        Date(SystemTime::now())
+    }
+}
+
+impl TypedHeader for Date {
+    const NAME: HeaderName = HeaderName::DATE;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let value = single_value(&Self::NAME, values)?;
+        parse_http_date(value).map(Date).ok_or_else(|| invalid(&Self::NAME))
+    }
+
+    fn encode(&self) -> String {
+        format_http_date(self.0)
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Formats a time as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+/// Times before the epoch are clamped to it.
+pub fn format_http_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let days = (secs / 86400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let rem = secs % 86400;
+    format!("{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Parses any of the three date formats recipients must accept: IMF-fixdate,
+/// the obsolete RFC 850 format and asctime.
+pub fn parse_http_date(s: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    let (day, month, year, time) = match parts.as_slice() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        [_, day, month, year, time, "GMT"] => (digits(day, 2..=2)?, *month, digits(year, 4..=4)?, *time),
+        // Sunday, 06-Nov-94 08:49:37 GMT
+        [_, date, time, "GMT"] => {
+            let mut date = date.split('-');
+            let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+            let year = rfc850_year(digits(year, 2..=2)?, current_year());
+            (digits(day, 2..=2)?, month, year, *time)
+        },
+        // Sun Nov  6 08:49:37 1994
+        [_, month, day, time, year] => (digits(day, 1..=2)?, *month, digits(year, 4..=4)?, *time),
+        _ => return None,
+    };
+
+    let month = MONTHS.iter().position(|m| *m == month)? as u32 + 1;
+    let mut hms = time.split(':').map(|p| digits::<u64>(p, 2..=2));
+    let (h, m, sec) = (hms.next()??, hms.next()??, hms.next()??);
+    if hms.next().is_some() || !(1..=days_in_month(year, month)).contains(&day) || h > 23 || m > 59 || sec > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    let secs = u64::try_from(days).ok()?
+        .checked_mul(86400)?
+        .checked_add(h * 3600 + m * 60 + sec)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Expands a two-digit RFC 850 year to the one in `current_year`'s century,
+/// unless that looks more than 50 years ahead, in which case it is the most
+/// recent past year with those digits (RFC 9110 section 5.6.7).
+fn rfc850_year(two_digits: i64, current_year: i64) -> i64 {
+    let year = current_year - current_year % 100 + two_digits;
+    if year > current_year + 50 { year - 100 } else { year }
+}
+
+fn current_year() -> i64 {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    civil_from_days((secs / 86400) as i64).0
+}
+
+/// Parses a run of ASCII digits whose length is within `len`.
+fn digits<T: std::str::FromStr>(s: &str, len: std::ops::RangeInclusive<usize>) -> Option<T> {
+    if !len.contains(&s.len()) || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's proleptic Gregorian calendar conversions.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// `Connection`: the connection options, lowercased.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Connection(pub Vec<String>);
+
+impl Connection {
+    pub fn close() -> Self {
+        Connection(vec!["close".to_string()])
+    }
+
+    pub fn keep_alive() -> Self {
+        Connection(vec!["keep-alive".to_string()])
+    }
+
+    pub fn contains(&self, option: &str) -> bool {
+        self.0.iter().any(|o| o.eq_ignore_ascii_case(option))
+    }
+}
+
+impl TypedHeader for Connection {
+    const NAME: HeaderName = HeaderName::CONNECTION;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let options: Vec<String> = list_values(values).map(|v| v.to_ascii_lowercase()).collect();
+        if !options.iter().all(|o| is_token(o)) {
+            return Err(invalid(&Self::NAME));
+        }
+        Ok(Connection(options))
+    }
+
+    fn encode(&self) -> String {
+        self.0.join(", ")
+    }
+}
+
+/// `Transfer-Encoding`: the codings in the order they were applied.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransferEncoding(pub Vec<String>);
+
+impl TransferEncoding {
+    pub fn chunked() -> Self {
+        TransferEncoding(vec!["chunked".to_string()])
+    }
+
+    /// Whether chunked is the final coding, which is what frames the body.
+    pub fn is_chunked(&self) -> bool {
+        self.0.last().is_some_and(|c| c == "chunked")
+    }
+}
+
+impl TypedHeader for TransferEncoding {
+    const NAME: HeaderName = HeaderName::TRANSFER_ENCODING;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let codings: Vec<String> = list_values(values).map(|v| v.to_ascii_lowercase()).collect();
+        if !codings.iter().all(|c| is_token(c)) {
+            return Err(invalid(&Self::NAME));
+        }
+        Ok(TransferEncoding(codings))
+    }
+
+    fn encode(&self) -> String {
+        self.0.join(", ")
+    }
+}
+
+/// One media range from an `Accept` header, with its weight in thousandths.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MediaRange {
+    pub media_type: String,
+    pub quality: u16,
+}
+
+impl MediaRange {
+    fn matches(&self, mime_type: &str) -> bool {
+        let Some((ty, subtype)) = self.media_type.split_once('/') else {
+            return false;
+        };
+        let (want_ty, want_subtype) = mime_type.split_once('/').unwrap_or((mime_type, ""));
+        (ty == "*" || ty.eq_ignore_ascii_case(want_ty))
+            && (subtype == "*" || subtype.eq_ignore_ascii_case(want_subtype))
+    }
+}
+
+/// `Accept`: the media ranges a client will take, in the order sent.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Accept(pub Vec<MediaRange>);
+
+impl Accept {
+    /// The weight the client gives `mime_type`, from the most specific range
+    /// that matches it; `None` if nothing matches.
+    pub fn quality(&self, mime_type: &str) -> Option<u16> {
+        self.0.iter()
+            .filter(|r| r.matches(mime_type))
+            .max_by_key(|r| r.media_type.len() - r.media_type.matches('*').count())
+            .map(|r| r.quality)
+    }
+
+    pub fn accepts(&self, mime_type: &str) -> bool {
+        self.quality(mime_type).is_some_and(|q| q > 0)
+    }
+}
+
+impl TypedHeader for Accept {
+    const NAME: HeaderName = HeaderName::ACCEPT;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let mut ranges = Vec::new();
+        for item in list_values(values) {
+            let mut parts = item.split(';').map(|p| p.trim());
+            let media_type = parts.next().unwrap_or_default();
+            if !media_type.split_once('/').is_some_and(|(t, s)| is_token(t) && is_token(s)) {
+                return Err(invalid(&Self::NAME));
+            }
+
+            let mut quality = 1000;
+            for param in parts {
+                if let Some(q) = param.strip_prefix("q=").or_else(|| param.strip_prefix("Q=")) {
+                    quality = parse_quality(q).ok_or_else(|| invalid(&Self::NAME))?;
+                }
+            }
+            ranges.push(MediaRange { media_type: media_type.to_ascii_lowercase(), quality });
+        }
+        Ok(Accept(ranges))
+    }
+
+    fn encode(&self) -> String {
+        self.0.iter()
+            .map(|r| match r.quality {
+                1000 => r.media_type.clone(),
+                q => format!("{};q={}", r.media_type, format_quality(q)),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// qvalue = ( "0" [ "." 0*3DIGIT ] ) / ( "1" [ "." 0*3("0") ] )
+fn parse_quality(s: &str) -> Option<u16> {
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac_value = format!("{:0<3}", frac).parse::<u16>().ok()?;
+    match whole {
+        "0" => Some(frac_value),
+        "1" if frac_value == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+fn format_quality(q: u16) -> String {
+    let s = format!("0.{:03}", q);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `Cache-Control`: directives with their optional arguments.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CacheControl(pub Vec<(String, Option<String>)>);
+
+impl CacheControl {
+    pub fn new() -> Self {
+        CacheControl(Vec::new())
+    }
+
+    pub fn with_directive(mut self, name: &str, value: Option<&str>) -> Self {
+        self.0.push((name.to_ascii_lowercase(), value.map(str::to_string)));
+        self
+    }
+
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        self.with_directive("max-age", Some(&max_age.as_secs().to_string()))
+    }
+
+    pub fn with_no_cache(self) -> Self {
+        self.with_directive("no-cache", None)
+    }
+
+    pub fn with_no_store(self) -> Self {
+        self.with_directive("no-store", None)
+    }
+
+    pub fn directive(&self, name: &str) -> Option<Option<&str>> {
+        self.0.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_deref())
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.directive("max-age")??.parse().ok().map(Duration::from_secs)
+    }
+
+    pub fn no_cache(&self) -> bool {
+        self.directive("no-cache").is_some()
+    }
+
+    pub fn no_store(&self) -> bool {
+        self.directive("no-store").is_some()
+    }
+}
+
+impl TypedHeader for CacheControl {
+    const NAME: HeaderName = HeaderName::CACHE_CONTROL;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let mut cache_control = CacheControl::new();
+        for directive in list_values(values) {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => {
+                    let value = value.trim();
+                    (name.trim(), Some(value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)))
+                },
+                None => (directive, None),
+            };
+            if !is_token(name) {
+                return Err(invalid(&Self::NAME));
+            }
+            cache_control = cache_control.with_directive(name, value);
+        }
+        Ok(cache_control)
+    }
+
+    fn encode(&self) -> String {
+        self.0.iter()
+            .map(|(name, value)| match value {
+                Some(v) if is_token(v) => format!("{}={}", name, v),
+                Some(v) => format!("{}=\"{}\"", name, v),
+                None => name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `ETag`: an opaque validator, optionally weak.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ETag {
+    pub tag: String,
+    pub weak: bool,
+}
+
+impl ETag {
+    pub fn strong(tag: &str) -> Self {
+        ETag { tag: tag.to_string(), weak: false }
+    }
+
+    pub fn weak(tag: &str) -> Self {
+        ETag { tag: tag.to_string(), weak: true }
+    }
+
+    /// Strong comparison (RFC 9110 section 8.8.3.2): both strong and equal.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison: equal tags, whatever their weakness.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl TypedHeader for ETag {
+    const NAME: HeaderName = HeaderName::ETAG;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let value = single_value(&Self::NAME, values)?;
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let tag = quoted.strip_prefix('"').and_then(|t| t.strip_suffix('"'))
+            .filter(|t| !t.contains('"') && !t.bytes().any(|b| b.is_ascii_control() || b == b' '))
+            .ok_or_else(|| invalid(&Self::NAME))?;
+        Ok(ETag { tag: tag.to_string(), weak })
+    }
+
+    fn encode(&self) -> String {
+        if self.weak {
+            format!("W/\"{}\"", self.tag)
+        } else {
+            format!("\"{}\"", self.tag)
+        }
+    }
+}
+
+/// One range from a `Range: bytes=...` header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ByteRange {
+    /// `first-last`, both inclusive.
+    FromTo(u64, u64),
+    /// `first-`, to the end.
+    From(u64),
+    /// `-n`, the final n bytes.
+    Last(u64),
+}
+
+impl ByteRange {
+    /// The inclusive `(first, last)` offsets this range covers in a
+    /// representation of `len` bytes, or `None` if it is unsatisfiable.
+    pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        match *self {
+            ByteRange::FromTo(first, _) | ByteRange::From(first) if first >= len => None,
+            ByteRange::FromTo(first, last) => Some((first, last.min(len - 1))),
+            ByteRange::From(first) => Some((first, len - 1)),
+            ByteRange::Last(0) => None,
+            ByteRange::Last(_) if len == 0 => None,
+            ByteRange::Last(n) => Some((len.saturating_sub(n), len - 1)),
+        }
+    }
+}
+
+/// `Range`. Only the `bytes` unit is understood.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Range(pub Vec<ByteRange>);
+
+impl Range {
+    pub fn bytes(first: u64, last: Option<u64>) -> Self {
+        match last {
+            Some(last) => Range(vec![ByteRange::FromTo(first, last)]),
+            None => Range(vec![ByteRange::From(first)]),
+        }
+    }
+}
+
+impl TypedHeader for Range {
+    const NAME: HeaderName = HeaderName::RANGE;
+
+    fn decode<'a, I: Iterator<Item = &'a str>>(values: I) -> Result<Self, ParseError> {
+        let value = single_value(&Self::NAME, values)?;
+        let (unit, specs) = value.split_once('=').ok_or_else(|| invalid(&Self::NAME))?;
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return Err(invalid(&Self::NAME));
+        }
+
+        let parse_pos = |s: &str| -> Result<u64, ParseError> {
+            if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid(&Self::NAME));
+            }
+            s.parse().map_err(|_| invalid(&Self::NAME))
+        };
+
+        let mut ranges = Vec::new();
+        for spec in specs.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (first, last) = spec.split_once('-').ok_or_else(|| invalid(&Self::NAME))?;
+            let range = match (first, last) {
+                ("", last) => ByteRange::Last(parse_pos(last)?),
+                (first, "") => ByteRange::From(parse_pos(first)?),
+                (first, last) => {
+                    let (first, last) = (parse_pos(first)?, parse_pos(last)?);
+                    if last < first {
+                        return Err(invalid(&Self::NAME));
+                    }
+                    ByteRange::FromTo(first, last)
+                },
+            };
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err(invalid(&Self::NAME));
+        }
+        Ok(Range(ranges))
+    }
+
+    fn encode(&self) -> String {
+        let specs: Vec<String> = self.0.iter()
+            .map(|r| match r {
+                ByteRange::FromTo(first, last) => format!("{}-{}", first, last),
+                ByteRange::From(first) => format!("{}-", first),
+                ByteRange::Last(n) => format!("-{}", n),
+            })
+            .collect();
+        format!("bytes={}", specs.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers(fields: &[(&'static str, &str)]) -> Headers {
+        let mut headers = Headers::new();
+        for (name, value) in fields {
+            headers.append(*name, value.to_string());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_typed_get_and_insert() {
+        let mut h = Headers::new();
+        assert_eq!(None, h.typed_get::<ContentLength>());
+
+        h.typed_insert(ContentLength(42));
+        h.typed_insert(ContentType::html().with_param("charset", "utf-8"));
+        assert_eq!(Some("42"), h.get_str("content-length"));
+        assert_eq!(Some("text/html; charset=utf-8"), h.get_str("content-type"));
+        assert_eq!(Some(ContentLength(42)), h.typed_get());
+        assert_eq!(Some("utf-8"), h.typed_get::<ContentType>().unwrap().charset());
+
+        h.typed_insert(ContentLength(7));
+        assert_eq!(1, h.get_all("content-length").count());
+    }
+
+    #[test]
+    fn test_content_length() {
+        assert_eq!(Some(ContentLength(5)), headers(&[("Content-Length", "5"), ("Content-Length", "5, 5")]).typed_try_get().unwrap());
+        assert_eq!(headers(&[("Content-Length", "5, 6")]).typed_try_get::<ContentLength>().unwrap_err(), ParseErrorKind::ConflictingContentLength);
+        assert_eq!(headers(&[("Content-Length", "+5")]).typed_try_get::<ContentLength>().unwrap_err(), ParseErrorKind::InvalidContentLength);
+        assert_eq!(None, headers(&[("Content-Length", "abc")]).typed_get::<ContentLength>());
+    }
+
+    #[test]
+    fn test_content_type() {
+        let ct: ContentType = headers(&[("Content-Type", "Text/HTML; Charset=\"UTF-8\"")]).typed_get().unwrap();
+        assert_eq!("text/html", ct.mime_type());
+        assert_eq!(Some("UTF-8"), ct.charset());
+        assert!(headers(&[("Content-Type", "texthtml")]).typed_get::<ContentType>().is_none());
+    }
+
+    #[test]
+    fn test_host() {
+        let host: Host = headers(&[("Host", "example.com:8080")]).typed_get().unwrap();
+        assert_eq!(Host { host: "example.com".to_string(), port: Some(8080) }, host);
+        let host: Host = headers(&[("Host", "[::1]")]).typed_get().unwrap();
+        assert_eq!(None, host.port);
+        assert_eq!("[::1]", host.encode());
+
+        assert!(headers(&[("Host", "a"), ("Host", "b")]).typed_get::<Host>().is_none());
+        assert!(headers(&[("Host", "example.com:http")]).typed_get::<Host>().is_none());
+    }
+
+    #[test]
+    fn test_date_formats() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT", Date(expected).encode());
+        assert_eq!(Some(expected), parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(Some(expected), parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"));
+        assert_eq!(Some(expected), parse_http_date("Sun Nov  6 08:49:37 1994"));
+        assert_eq!(None, parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"));
+        assert_eq!("Thu, 29 Feb 2024 00:00:00 GMT", format_http_date(UNIX_EPOCH + Duration::from_secs(1709164800)));
+    }
+
+    #[test]
+    fn test_rfc850_year_window() {
+        assert_eq!(2076, rfc850_year(76, 2026));
+        assert_eq!(1977, rfc850_year(77, 2026));
+        assert_eq!(2000, rfc850_year(0, 2026));
+        assert_eq!(1994, rfc850_year(94, 2026));
+        // the window moves with the current year rather than pivoting on 1970
+        assert_eq!(2070, rfc850_year(70, 2026));
+        assert_eq!(2099, rfc850_year(99, 2071));
+        assert_eq!(2021, rfc850_year(21, 2071));
+    }
+
+    #[test]
+    fn test_invalid_dates_rejected() {
+        assert_eq!(None, parse_http_date("Sun, 06 Nov 99999999999999 08:49:37 GMT"));
+        assert_eq!(None, parse_http_date("Sun Nov  6 08:49:37 99999999999999"));
+        assert_eq!(None, parse_http_date("Sun, 06 Nov 94 08:49:37 GMT"));
+        assert_eq!(None, parse_http_date("Sat, 31 Feb 2024 00:00:00 GMT"));
+        assert_eq!(None, parse_http_date("Sat, 29 Feb 2023 00:00:00 GMT"));
+        assert_eq!(None, parse_http_date("Tue, 31 Apr 2024 00:00:00 GMT"));
+        assert_eq!(None, parse_http_date("Sun, +6 Nov 1994 08:49:37 GMT"));
+        assert!(parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT").is_some());
+    }
+
+    #[test]
+    fn test_connection_and_transfer_encoding() {
+        let connection: Connection = headers(&[("Connection", "Upgrade"), ("Connection", "Close")]).typed_get().unwrap();
+        assert!(connection.contains("close"));
+        assert_eq!("upgrade, close", connection.encode());
+
+        let te: TransferEncoding = headers(&[("Transfer-Encoding", "gzip, Chunked")]).typed_get().unwrap();
+        assert!(te.is_chunked());
+        assert!(!TransferEncoding(vec!["chunked".to_string(), "gzip".to_string()]).is_chunked());
+    }
+
+    #[test]
+    fn test_accept() {
+        let accept: Accept = headers(&[("Accept", "text/*;q=0.5, text/html, */*;q=0"), ("Accept", "application/json;q=0.8")]).typed_get().unwrap();
+        assert_eq!(Some(1000), accept.quality("text/html"));
+        assert_eq!(Some(500), accept.quality("text/plain"));
+        assert_eq!(Some(800), accept.quality("application/json"));
+        assert!(!accept.accepts("image/png"));
+        assert_eq!("text/*;q=0.5, text/html, */*;q=0, application/json;q=0.8", accept.encode());
+
+        assert!(headers(&[("Accept", "text/html;q=1.5")]).typed_get::<Accept>().is_none());
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let cc: CacheControl = headers(&[("Cache-Control", "no-cache, max-age=60, private=\"Set-Cookie\"")]).typed_get().unwrap();
+        assert!(cc.no_cache());
+        assert!(!cc.no_store());
+        assert_eq!(Some(Duration::from_secs(60)), cc.max_age());
+        assert_eq!(Some(Some("Set-Cookie")), cc.directive("private"));
+
+        let cc = CacheControl::new().with_no_store().with_max_age(Duration::from_secs(0));
+        assert_eq!("no-store, max-age=0", cc.encode());
+    }
+
+    #[test]
+    fn test_etag() {
+        let tag: ETag = headers(&[("ETag", "W/\"xyzzy\"")]).typed_get().unwrap();
+        assert!(tag.weak);
+        assert!(tag.weak_eq(&ETag::strong("xyzzy")));
+        assert!(!tag.strong_eq(&ETag::strong("xyzzy")));
+        assert_eq!("W/\"xyzzy\"", tag.encode());
+        assert!(headers(&[("ETag", "xyzzy")]).typed_get::<ETag>().is_none());
+    }
+
+    #[test]
+    fn test_range() {
+        let range: Range = headers(&[("Range", "bytes=0-499, 500-, -200")]).typed_get().unwrap();
+        assert_eq!(vec![ByteRange::FromTo(0, 499), ByteRange::From(500), ByteRange::Last(200)], range.0);
+        assert_eq!(Some((0, 99)), range.0[0].resolve(100));
+        assert_eq!(None, range.0[1].resolve(100));
+        assert_eq!(Some((0, 99)), range.0[2].resolve(100));
+        assert_eq!("bytes=0-499,500-,-200", range.encode());
+
+        assert!(headers(&[("Range", "bytes=5-1")]).typed_get::<Range>().is_none());
+        assert!(headers(&[("Range", "items=0-1")]).typed_get::<Range>().is_none());
+    }
+}
@@ -0,0 +1,51 @@
+use crate::http::request::{ParseError, ParseErrorKind};
+
+/// HTTP protocol version. Only the two HTTP/1.x versions are spoken here.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, PartialOrd, Ord, Hash)]
+pub enum Version {
+    Http10,
+    #[default]
+    Http11,
+}
+
+impl Version {
+    /// The bare version number, e.g. `1.1`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::Http10 => "1.0",
+            Version::Http11 => "1.1",
+        }
+    }
+
+    /// Whether connections stay open unless `Connection: close` is sent.
+    pub fn keep_alive_by_default(&self) -> bool {
+        *self >= Version::Http11
+    }
+
+    /// Whether the chunked transfer coding can be used.
+    pub fn supports_chunked(&self) -> bool {
+        *self >= Version::Http11
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1.0" => Ok(Version::Http10),
+            "1.1" => Ok(Version::Http11),
+            // a version we can't speak, rather than garbage, gets a 505
+            _ if s.len() == 3 && s.as_bytes()[0].is_ascii_digit() && s.as_bytes()[1] == b'.' && s.as_bytes()[2].is_ascii_digit() => {
+                Err(ParseErrorKind::UnsupportedVersion.into())
+            },
+            _ => Err(ParseErrorKind::InvalidVersion.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP/{}", self.as_str())
+    }
+}